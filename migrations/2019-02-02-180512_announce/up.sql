@@ -0,0 +1,6 @@
+CREATE TABLE "announce" (
+  "channel"  text  NOT NULL,
+  "tag"      text  NOT NULL  DEFAULT '',
+  "network"  text  NOT NULL  DEFAULT '',
+  PRIMARY KEY ("network", "channel", "tag")
+);
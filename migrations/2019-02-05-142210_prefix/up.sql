@@ -0,0 +1,7 @@
+CREATE TABLE "prefix" (
+  "channel"   text     NOT NULL,
+  "prefixes"  text     NOT NULL,
+  "brackets"  boolean  NOT NULL  DEFAULT TRUE,
+  "network"   text     NOT NULL  DEFAULT '',
+  PRIMARY KEY ("network", "channel")
+);
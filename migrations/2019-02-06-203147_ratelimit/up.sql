@@ -0,0 +1,7 @@
+CREATE TABLE "ratelimit" (
+  "channel"      text     NOT NULL,
+  "per_user"     integer  NOT NULL,
+  "per_channel"  integer  NOT NULL,
+  "network"      text     NOT NULL  DEFAULT '',
+  PRIMARY KEY ("network", "channel")
+);
@@ -0,0 +1,6 @@
+CREATE TABLE "account" (
+  "name"     text  NOT NULL,
+  "auth"     text  NOT NULL,
+  "network"  text  NOT NULL  DEFAULT '',
+  PRIMARY KEY ("network", "name")
+);
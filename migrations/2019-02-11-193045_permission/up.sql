@@ -0,0 +1,7 @@
+CREATE TABLE "permission" (
+  "channel"  text  NOT NULL,
+  "command"  text  NOT NULL,
+  "auth"     text  NOT NULL,
+  "network"  text  NOT NULL  DEFAULT '',
+  PRIMARY KEY ("network", "channel", "command")
+);
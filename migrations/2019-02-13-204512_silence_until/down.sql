@@ -0,0 +1 @@
+ALTER TABLE "silence" DROP COLUMN "until";
@@ -0,0 +1 @@
+ALTER TABLE "silence" ADD COLUMN "until" timestamp;
@@ -0,0 +1,6 @@
+CREATE TABLE "ignore" (
+  "channel"  text  NOT NULL,
+  "mask"     text  NOT NULL,
+  "network"  text  NOT NULL  DEFAULT '',
+  PRIMARY KEY ("network", "channel", "mask")
+);
@@ -0,0 +1,20 @@
+DELETE FROM "memo" WHERE "network" <> '';
+ALTER TABLE "memo" DROP CONSTRAINT "memo_pkey";
+ALTER TABLE "memo" ADD PRIMARY KEY ("channel", "user");
+ALTER TABLE "memo" DROP COLUMN "network";
+
+DELETE FROM "seen" WHERE "network" <> '';
+ALTER TABLE "seen" DROP CONSTRAINT "seen_pkey";
+ALTER TABLE "seen" ADD PRIMARY KEY ("channel", "user");
+ALTER TABLE "seen" DROP COLUMN "network";
+
+DELETE FROM "silence" WHERE "network" <> '';
+ALTER TABLE "silence" DROP CONSTRAINT "silence_pkey";
+ALTER TABLE "silence" ADD PRIMARY KEY ("channel", "command");
+ALTER TABLE "silence" DROP COLUMN "network";
+
+DELETE FROM "reminder" WHERE "network" <> '';
+ALTER TABLE "reminder" DROP COLUMN "network";
+
+DELETE FROM "tell" WHERE "network" <> '';
+ALTER TABLE "tell" DROP COLUMN "network";
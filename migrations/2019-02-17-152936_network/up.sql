@@ -0,0 +1,15 @@
+ALTER TABLE "memo" ADD COLUMN "network" text NOT NULL DEFAULT '';
+ALTER TABLE "memo" DROP CONSTRAINT "memo_pkey";
+ALTER TABLE "memo" ADD PRIMARY KEY ("network", "channel", "user");
+
+ALTER TABLE "seen" ADD COLUMN "network" text NOT NULL DEFAULT '';
+ALTER TABLE "seen" DROP CONSTRAINT "seen_pkey";
+ALTER TABLE "seen" ADD PRIMARY KEY ("network", "channel", "user");
+
+ALTER TABLE "silence" ADD COLUMN "network" text NOT NULL DEFAULT '';
+ALTER TABLE "silence" DROP CONSTRAINT "silence_pkey";
+ALTER TABLE "silence" ADD PRIMARY KEY ("network", "channel", "command");
+
+ALTER TABLE "reminder" ADD COLUMN "network" text NOT NULL DEFAULT '';
+
+ALTER TABLE "tell" ADD COLUMN "network" text NOT NULL DEFAULT '';
@@ -0,0 +1,539 @@
+use hashbrown::HashMap;
+use std::mem;
+
+use crate::error::*;
+use crate::text::Text;
+
+/// Splits a message into arguments the way a shell would. Double quotes group words into one
+/// argument, and a backslash escapes a quote, a backslash or a space. Apostrophes are left alone,
+/// and a quote that's never closed runs to the end of the message.
+pub fn tokenize(s: &str) -> Vec<String> {
+    split(s).into_iter().map(|(_, token)| token).collect()
+}
+
+/// Like `tokenize`, but also says where in the message each argument starts.
+pub fn split(s: &str) -> Vec<(usize, String)> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    // Whether there's a token in progress, so that "" still counts as an argument.
+    let mut started = false;
+    let mut start = 0;
+    let mut quoted = false;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !started {
+            start = i;
+        }
+        match c {
+            '\\' => match chars.peek() {
+                Some(&(_, next)) if next == '"' || next == '\\' || next == ' ' => {
+                    token.push(next);
+                    chars.next();
+                },
+                _ => token.push(c)
+            },
+            '"' => quoted = !quoted,
+            ' ' if !quoted => {
+                if started {
+                    tokens.push((start, mem::replace(&mut token, String::new())));
+                    started = false;
+                }
+                continue
+            },
+            _ => token.push(c)
+        }
+        started = true;
+    }
+    if started {
+        tokens.push((start, token));
+    }
+    tokens
+}
+
+/// Undoes `tokenize` for an argument that will be run again later.
+pub fn quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c == ' ' || c == '"' || c == '\\') {
+        arg.to_owned()
+    } else {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// What kind of value an argument takes. Arguments that don't fit are rejected with an error
+/// that says what was expected.
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    Word,
+    Number,
+    Channel,
+    Choice(&'static [&'static str]),
+    /// A description of what's expected, and a test for it.
+    Check(&'static str, fn(&str) -> bool)
+}
+
+impl Kind {
+    /// Optional parameters are skipped if the argument obviously isn't for them.
+    fn claims(self, arg: &str) -> bool {
+        match self {
+            Kind::Channel => arg.starts_with('#'),
+            _             => true
+        }
+    }
+
+    /// Arguments that can't be mistaken for anything else can go anywhere.
+    fn distinct(self) -> bool {
+        match self {
+            Kind::Channel => true,
+            _             => false
+        }
+    }
+
+    fn check(self, name: &str, arg: &str) -> Result<(), Error> {
+        let (fits, expected) = match self {
+            Kind::Word           => (true, String::new()),
+            Kind::Number         => (arg.parse::<i64>().is_ok(), "a number".to_owned()),
+            Kind::Channel        => (arg.starts_with('#'), "a channel".to_owned()),
+            Kind::Choice(xs)     => (xs.iter().any(|x| x.eq_ignore_ascii_case(arg)), either(xs)),
+            Kind::Check(what, f) => (f(arg), what.to_owned())
+        };
+        if fits {
+            Ok(())
+        } else {
+            Err(BadArgs(Text::new()
+                .plain(format!("{} should be {}, not ", name, expected))
+                .bold(arg)
+                .plain(".")
+            ))
+        }
+    }
+}
+
+fn either(xs: &[&str]) -> String {
+    match xs.split_last() {
+        None               => String::new(),
+        Some((last, []))   => (*last).to_owned(),
+        Some((last, init)) => format!("{} or {}", init.join(", "), last)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arity {
+    One,
+    Optional,
+    Rest,
+    OptionalRest
+}
+
+#[derive(Debug, Clone)]
+struct Param {
+    name:  &'static str,
+    kind:  Kind,
+    arity: Arity
+}
+
+impl Param {
+    fn usage(&self) -> String {
+        let name = match self.kind {
+            Kind::Choice(xs) => format!("({})", xs.join("|")),
+            _                => format!("<{}>", self.name)
+        };
+        match self.arity {
+            Arity::One | Arity::Rest              => name,
+            Arity::Optional | Arity::OptionalRest => format!("[{}]", name)
+        }
+    }
+    fn label(&self) -> String {
+        format!("<{}>", self.name)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Flag {
+    short: &'static str,
+    long:  &'static str,
+    hint:  Option<&'static str>,
+    kind:  Kind,
+    multi: bool
+}
+
+impl Flag {
+    fn name(&self) -> String {
+        if self.short.is_empty() { format!("--{}", self.long) } else { format!("-{}", self.short) }
+    }
+    fn usage(&self) -> String {
+        let usage = match self.hint {
+            None       => format!("[{}]", self.name()),
+            Some(hint) => format!("[{} <{}>]", self.name(), hint)
+        };
+        if self.multi { format!("{}...", usage) } else { usage }
+    }
+    fn matches(&self, arg: &str) -> bool {
+        if arg.starts_with("--") {
+            !self.long.is_empty() && arg[2..] == *self.long
+        } else {
+            !self.short.is_empty() && arg[1..] == *self.short
+        }
+    }
+}
+
+/// Looks like an option, rather than a negative number or a lone dash.
+fn is_flag(arg: &str) -> bool {
+    arg.starts_with('-') && arg[1..].chars().next().map_or(false, |c| !c.is_ascii_digit())
+}
+
+/// A declarative description of a command's arguments, used both to parse them and to generate
+/// the command's usage text. Options can appear anywhere. Parameters are filled in order.
+#[derive(Debug, Clone, Default)]
+pub struct Spec {
+    params: Vec<Param>,
+    flags:  Vec<Flag>,
+    subs:   Vec<(&'static str, Spec)>
+}
+
+impl Spec {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(mut self, name: &'static str, kind: Kind, arity: Arity) -> Self {
+        self.params.push(Param { name, kind, arity });
+        self
+    }
+    pub fn param(self, name: &'static str, kind: Kind) -> Self {
+        self.push(name, kind, Arity::One)
+    }
+    pub fn optional(self, name: &'static str, kind: Kind) -> Self {
+        self.push(name, kind, Arity::Optional)
+    }
+    /// One or more words, which must come last.
+    pub fn rest(self, name: &'static str) -> Self {
+        self.push(name, Kind::Word, Arity::Rest)
+    }
+    /// Any number of words, which must come last.
+    pub fn optional_rest(self, name: &'static str) -> Self {
+        self.push(name, Kind::Word, Arity::OptionalRest)
+    }
+
+    fn push_flag(mut self, flag: Flag) -> Self {
+        self.flags.push(flag);
+        self
+    }
+    /// An option that takes no value. Either name may be empty.
+    pub fn flag(self, short: &'static str, long: &'static str) -> Self {
+        self.push_flag(Flag { short, long, hint: None, kind: Kind::Word, multi: false })
+    }
+    /// An option that takes a value.
+    pub fn opt(self, short: &'static str, long: &'static str, hint: &'static str, kind: Kind)
+    -> Self {
+        self.push_flag(Flag { short, long, hint: Some(hint), kind, multi: false })
+    }
+    /// An option that takes a value and can be given more than once.
+    pub fn multi(self, short: &'static str, long: &'static str, hint: &'static str, kind: Kind)
+    -> Self {
+        self.push_flag(Flag { short, long, hint: Some(hint), kind, multi: true })
+    }
+    /// A subcommand, chosen when it's the first argument.
+    pub fn sub(mut self, name: &'static str, spec: Spec) -> Self {
+        self.subs.push((name, spec));
+        self
+    }
+
+    pub fn usage(&self, cmd: &str) -> String {
+        let mut usage: Vec<String> = self.params.iter().map(Param::usage).collect();
+        usage.extend(self.flags.iter().map(Flag::usage));
+        let mut usage = usage.join(" ");
+        for (name, sub) in &self.subs {
+            usage.push_str(&format!(" | {} {} {}", cmd, name, sub.usage(cmd)).trim_end());
+        }
+        usage.trim_start().to_owned()
+    }
+
+    pub fn parse(&self, args: &[&str]) -> Result<Args, Error> {
+        self.parse_typed(args, &[])
+    }
+
+    /// Parses the arguments of a message. Unlike `parse`, a parameter that takes the rest of the
+    /// words gets them exactly as they were typed, quotes and all.
+    pub fn parse_message(&self, message: &str) -> Result<Args, Error> {
+        let tokens = split(message);
+        let args: Vec<&str> = tokens.iter().map(|(_, x)| x.as_str()).collect();
+        let typed: Vec<&str> = tokens.iter().map(|&(i, _)| &message[i..]).collect();
+        self.parse_typed(&args, &typed)
+    }
+
+    /// `typed` holds the rest of the message as it was typed from each argument on, if known.
+    fn parse_typed(&self, args: &[&str], typed: &[&str]) -> Result<Args, Error> {
+        if let Some((first, rest)) = args.split_first() {
+            for &(name, ref sub) in &self.subs {
+                if first.eq_ignore_ascii_case(name) {
+                    let mut parsed = sub.parse_typed(rest, typed.get(1..).unwrap_or(&[]))?;
+                    parsed.sub = Some(name);
+                    return Ok(parsed)
+                }
+            }
+        }
+
+        let mut values: HashMap<usize, Vec<String>> = HashMap::new();
+        let mut positional = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            let arg = args[i];
+            i += 1;
+            if self.flags.is_empty() || !is_flag(arg) {
+                positional.push(arg);
+                continue
+            }
+            let (j, flag) = self.flags
+                .iter()
+                .enumerate()
+                .find(|(_, x)| x.matches(arg))
+                .ok_or_else(|| BadArgs(
+                    Text::new().plain("I don't know the option ").bold(arg).plain(".")
+                ))?;
+            let entry = values.entry(j).or_insert_with(Vec::new);
+            if let Some(hint) = flag.hint {
+                if !flag.multi && !entry.is_empty() {
+                    return Err(BadArgs(format!("{} can only be used once.", flag.name()).into()))
+                }
+                let value = args
+                    .get(i)
+                    .ok_or_else(|| BadArgs(format!("{} needs a <{}>.", flag.name(), hint).into()))?;
+                i += 1;
+                flag.kind.check(&flag.name(), value)?;
+                entry.push((*value).to_owned());
+            }
+        }
+        let mut parsed = Args::default();
+        for (j, vals) in values {
+            let flag = &self.flags[j];
+            for &name in &[flag.short, flag.long] {
+                if !name.is_empty() {
+                    parsed.opts.insert(name, vals.clone());
+                }
+            }
+        }
+        self.assign(&positional, args, typed, parsed)
+    }
+
+    fn assign(&self, positional: &[&str], args: &[&str], typed: &[&str], mut parsed: Args)
+    -> Result<Args, Error> {
+        let mut positional = positional.to_vec();
+        for param in &self.params {
+            if param.arity == Arity::Optional && param.kind.distinct() {
+                if let Some(i) = positional.iter().position(|x| param.kind.claims(x)) {
+                    parsed.params.insert(param.name, positional.remove(i).to_owned());
+                }
+            }
+        }
+        for (i, param) in self.params.iter().enumerate() {
+            if param.arity == Arity::Optional && param.kind.distinct() {
+                continue
+            }
+            let needed = self.params[i+1..]
+                .iter()
+                .filter(|x| x.arity == Arity::One || x.arity == Arity::Rest)
+                .count();
+            match param.arity {
+                Arity::One | Arity::Optional => {
+                    let arg = match positional.first() {
+                        Some(arg) if param.arity == Arity::One => arg,
+                        Some(arg) if positional.len() > needed && param.kind.claims(arg) => arg,
+                        Some(_) => continue,
+                        None if param.arity == Arity::One =>
+                            return Err(BadArgs(format!("Missing {}.", param.label()).into())),
+                        None => continue
+                    };
+                    param.kind.check(&param.label(), arg)?;
+                    parsed.params.insert(param.name, (*arg).to_owned());
+                    positional.remove(0);
+                },
+                Arity::Rest | Arity::OptionalRest => {
+                    if positional.is_empty() {
+                        if param.arity == Arity::Rest {
+                            return Err(BadArgs(format!("Missing {}.", param.label()).into()))
+                        }
+                        continue
+                    }
+                    // The words are kept as typed unless something was picked out from among them.
+                    let tail = args.len() - positional.len();
+                    let text = match typed.get(tail) {
+                        Some(text) if args[tail..] == positional[..] => text.trim_end().to_owned(),
+                        _                                           => positional.join(" ")
+                    };
+                    parsed.params.insert(param.name, text);
+                    parsed.free = positional.drain(..).map(ToOwned::to_owned).collect();
+                }
+            }
+        }
+        if positional.is_empty() {
+            Ok(parsed)
+        } else {
+            Err(BadArgs(
+                Text::new().plain("I didn't expect ").bold(positional.join(" ")).plain(".")
+            ))
+        }
+    }
+}
+
+/// Parsed arguments. Options can be looked up by either their short or long names.
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    /// The subcommand that was used, if any.
+    pub sub:  Option<&'static str>,
+    /// The words of the last parameter, if it takes any number of words.
+    pub free: Vec<String>,
+    params:   HashMap<&'static str, String>,
+    opts:     HashMap<&'static str, Vec<String>>
+}
+
+impl Args {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+    /// A parameter that's required by the spec, so it's always there after parsing.
+    pub fn text(&self, name: &str) -> &str {
+        self.get(name).unwrap_or("")
+    }
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty() && self.opts.is_empty()
+    }
+    pub fn opt_present(&self, name: &str) -> bool {
+        self.opts.contains_key(name)
+    }
+    pub fn opt_str(&self, name: &str) -> Option<String> {
+        self.opts.get(name)?.last().cloned()
+    }
+    pub fn opt_strs(&self, name: &str) -> Vec<String> {
+        self.opts.get(name).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::Format;
+    use crate::util::own;
+
+    fn spec() -> Spec {
+        Spec::new()
+            .optional("channel", Kind::Channel)
+            .param("user", Kind::Word)
+            .optional_rest("message")
+            .flag("f", "first")
+            .opt("n", "count", "count", Kind::Number)
+            .multi("t", "tag", "tag", Kind::Word)
+            .opt("", "sort", "order", Kind::Choice(&["date", "rating"]))
+    }
+
+    fn error(args: &[&str]) -> String {
+        match spec().parse(args) {
+            Err(BadArgs(e)) => e.render(Format::Irc),
+            x               => panic!("Expected an error, got {:?}", x)
+        }
+    }
+
+    #[test]
+    fn generates_usage() {
+        assert_eq!(
+            spec().usage("x"),
+            "[<channel>] <user> [<message>] [-f] [-n <count>] [-t <tag>]... [--sort <order>]"
+        );
+        let subs = Spec::new()
+            .optional("user", Kind::Word)
+            .sub("del", Spec::new().param("user", Kind::Word));
+        assert_eq!(subs.usage("memo"), "[<user>] | memo del <user>");
+        assert_eq!(Spec::new().sub("reset", Spec::new()).usage("x"), "| x reset");
+        let choice = Spec::new().param("setting", Kind::Choice(&["on", "off"]));
+        assert_eq!(choice.usage("x"), "(on|off)");
+    }
+
+    #[test]
+    fn parses_params_and_options() {
+        let args = spec().parse(&["#site19", "-t", "a", "Alice", "hi", "-f", "there", "--tag", "b"])
+            .unwrap();
+        assert_eq!(args.get("channel"), Some("#site19"));
+        assert_eq!(args.get("user"), Some("Alice"));
+        assert_eq!(args.get("message"), Some("hi there"));
+        assert_eq!(args.free, vec!["hi".to_owned(), "there".to_owned()]);
+        assert!(args.opt_present("first") && args.opt_present("f"));
+        assert_eq!(args.opt_strs("tag"), vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!(args.opt_str("n"), None);
+    }
+
+    #[test]
+    fn skips_optional_params() {
+        let args = spec().parse(&["Alice"]).unwrap();
+        assert_eq!(args.get("channel"), None);
+        assert_eq!(args.get("user"), Some("Alice"));
+        assert!(args.free.is_empty());
+    }
+
+    #[test]
+    fn finds_channels_anywhere() {
+        let args = spec().parse(&["Alice", "hi", "#site19"]).unwrap();
+        assert_eq!(args.get("channel"), Some("#site19"));
+        assert_eq!(args.get("message"), Some("hi"));
+    }
+
+    #[test]
+    fn parses_subcommands() {
+        let spec = Spec::new().sub("del", Spec::new().param("user", Kind::Word));
+        let args = spec.parse(&["DEL", "Alice"]).unwrap();
+        assert_eq!(args.sub, Some("del"));
+        assert_eq!(args.get("user"), Some("Alice"));
+    }
+
+    #[test]
+    fn reports_precise_errors() {
+        assert_eq!(error(&[]), "Missing <user>.");
+        assert_eq!(error(&["Alice", "-x"]), "I don't know the option \x02-x\x02.");
+        assert_eq!(error(&["Alice", "-n"]), "-n needs a <count>.");
+        assert_eq!(error(&["Alice", "-n", "lots"]), "-n should be a number, not \x02lots\x02.");
+        assert_eq!(error(&["Alice", "-n", "1", "-n", "2"]), "-n can only be used once.");
+        assert_eq!(
+            error(&["Alice", "--sort", "title"]), 
+            "--sort should be date or rating, not \x02title\x02."
+        );
+        match Spec::new().param("user", Kind::Word).parse(&["a", "b"]) {
+            Err(BadArgs(e)) => assert_eq!(e.render(Format::Irc), "I didn't expect \x02b\x02."),
+            x               => panic!("Expected an error, got {:?}", x)
+        }
+    }
+
+    #[test]
+    fn tokenizes_quotes_and_escapes() {
+        assert_eq!(
+            tokenize(r#"  -e "the end"  a\ b \"c\" d's"#),
+            own(&["-e", "the end", "a b", "\"c\"", "d's"])
+        );
+        assert_eq!(tokenize(r#"x "" "unclosed y"#), own(&["x", "", "unclosed y"]));
+        assert_eq!(tokenize(r"C:\x"), own(&[r"C:\x"]));
+    }
+
+    #[test]
+    fn keeps_the_rest_as_typed() {
+        let args = spec().parse_message(r#"Alice she said "hi"  to\ me "#).unwrap();
+        assert_eq!(args.text("message"), r#"she said "hi"  to\ me"#);
+        assert_eq!(args.free, own(&["she", "said", "hi", "to me"]));
+        let args = spec().parse_message(r#"Alice -t x 5" tall"#).unwrap();
+        assert_eq!(args.text("message"), r#"5" tall"#);
+        let args = spec().parse_message(r#"Alice "a b" -f c"#).unwrap();
+        assert_eq!(args.text("message"), "a b c");
+    }
+
+    #[test]
+    fn quotes_what_tokenize_splits() {
+        for arg in &["plain", "the end", r#"say "hi""#, r"back\slash", ""] {
+            assert_eq!(tokenize(&quote(arg)), own(&[*arg]));
+        }
+        assert_eq!(quote("plain"), "plain");
+    }
+
+    #[test]
+    fn leaves_dashes_alone_without_options() {
+        let args = Spec::new().rest("dice").parse(&["d20", "-", "2", "-f"]).unwrap();
+        assert_eq!(args.text("dice"), "d20 - 2 -f");
+        assert!(spec().parse(&["Alice", "-5"]).is_ok());
+    }
+}
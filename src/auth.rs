@@ -1,9 +1,222 @@
+use hashbrown::{HashMap, HashSet};
+use irc::proto::{CapSubCommand, Command};
+use irc::proto::Command::{ACCOUNT, CAP, JOIN, KICK, NICK, PART, PRIVMSG, QUIT};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Context;
+
 pub use self::Auth::*;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Auth {
     Anyone,
+    Voice,
     HalfOp,
     Op,
     Owner
 }
+
+impl fmt::Display for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Anyone => "anyone",
+            Voice  => "voice",
+            HalfOp => "half-op",
+            Op     => "op",
+            Owner  => "owner"
+        })
+    }
+}
+
+/// Reads the names `Display` writes, which is how levels are stored in the `account` table.
+impl FromStr for Auth {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "anyone"  => Ok(Anyone),
+            "voice"   => Ok(Voice),
+            "half-op" => Ok(HalfOp),
+            "op"      => Ok(Op),
+            "owner"   => Ok(Owner),
+            _         => Err(())
+        }
+    }
+}
+
+/// A nick on a network. The same nick on two networks is usually two different people.
+type User = (String, String);
+
+/// The services account each user is logged in to. Servers with `account-tag` name it on every
+/// message, but `account-notify` and `extended-join` keep track of it between messages too.
+#[derive(Debug, Default)]
+pub struct Logins {
+    accounts: HashMap<User, String>,
+    /// The channels each user has been seen in. Once they've left all of them, the bot can no
+    /// longer see them log out or change nicks, so their login is forgotten.
+    channels: HashMap<User, HashSet<String>>,
+    /// Networks that acknowledged `account-tag`.
+    tagged:   HashSet<String>
+}
+
+impl Logins {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Watches for the server to acknowledge `account-tag`.
+    pub fn acknowledge(&mut self, network: &str, command: &Command) {
+        if let CAP(_, CapSubCommand::ACK, caps, suffix) = command {
+            let mut acked = caps.iter().chain(suffix.iter()).flat_map(|x| x.split_whitespace());
+            if acked.any(|x| x == "account-tag") {
+                self.tagged.insert(network.to_owned());
+            }
+        }
+    }
+
+    pub fn update(&mut self, ctx: &Context, command: &Command) {
+        let user = (ctx.network.to_owned(), ctx.user.to_owned());
+        match command {
+            ACCOUNT(account) => self.set(user, account),
+            JOIN(_, account, _) => {
+                self.join(user.to_owned(), &ctx.channel);
+                if let Some(account) = account {
+                    self.set(user, account);
+                }
+            },
+            PART(_, _) => self.leave(&user, &ctx.channel),
+            KICK(channel, nick, _) => {
+                let kicked = (ctx.network.to_owned(), nick.to_lowercase());
+                self.leave(&kicked, &channel.to_lowercase())
+            },
+            NICK(nick) => {
+                let renamed = (ctx.network.to_owned(), nick.to_lowercase());
+                if let Some(account) = self.accounts.remove(&user) {
+                    self.accounts.insert(renamed.to_owned(), account);
+                }
+                if let Some(channels) = self.channels.remove(&user) {
+                    self.channels.insert(renamed, channels);
+                }
+            },
+            QUIT(_) => self.forget(&user),
+            _ => {
+                if let PRIVMSG(_, _) = command {
+                    if ctx.channel != ctx.user {
+                        self.join(user.to_owned(), &ctx.channel);
+                    }
+                }
+                if let Some(account) = &ctx.account {
+                    self.accounts.insert(user, account.to_owned());
+                }
+            }
+        }
+    }
+
+    /// Servers use `*` to say a user has logged out.
+    fn set(&mut self, user: User, account: &str) {
+        if account == "*" {
+            self.accounts.remove(&user);
+        } else {
+            self.accounts.insert(user, account.to_lowercase());
+        }
+    }
+
+    fn join(&mut self, user: User, channel: &str) {
+        self.channels
+            .entry(user)
+            .or_insert_with(HashSet::new)
+            .insert(channel.to_owned());
+    }
+
+    fn leave(&mut self, user: &User, channel: &str) {
+        let left = match self.channels.get_mut(user) {
+            None           => true,
+            Some(channels) => { channels.remove(channel); channels.is_empty() }
+        };
+        if left {
+            self.forget(user);
+        }
+    }
+
+    fn forget(&mut self, user: &User) {
+        self.accounts.remove(user);
+        self.channels.remove(user);
+    }
+
+    pub fn get<'a>(&'a self, ctx: &'a Context) -> Option<&'a str> {
+        // With `account-tag`, a message without one is from someone who isn't logged in, even if
+        // someone using the same nick was before.
+        if self.tagged.contains(&ctx.network) {
+            return ctx.account.as_ref().map(String::as_str)
+        }
+        let user = (ctx.network.to_owned(), ctx.user.to_owned());
+        ctx.account.as_ref().or_else(|| self.accounts.get(&user)).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_levels() {
+        for auth in &[Anyone, Voice, HalfOp, Op, Owner] {
+            assert_eq!(auth.to_string().parse(), Ok(*auth));
+        }
+        assert_eq!("Half-Op".parse(), Ok(HalfOp));
+        assert_eq!("admin".parse::<Auth>(), Err(()));
+    }
+
+    #[test]
+    fn tracks_logins() {
+        let mut logins = Logins::new();
+        let ctx = Context::mock("#site19", "Alice");
+        logins.update(&ctx, &ACCOUNT("Alice_".to_owned()));
+        assert_eq!(logins.get(&ctx), Some("alice_"));
+        logins.update(&ctx, &NICK("Alice2".to_owned()));
+        assert_eq!(logins.get(&ctx), None);
+        let renamed = Context::mock("#site19", "Alice2");
+        assert_eq!(logins.get(&renamed), Some("alice_"));
+        logins.update(&renamed, &ACCOUNT("*".to_owned()));
+        assert_eq!(logins.get(&renamed), None);
+    }
+
+    #[test]
+    fn keeps_networks_apart() {
+        let mut logins = Logins::new();
+        let ctx = Context::mock("#site19", "Alice");
+        logins.update(&ctx, &ACCOUNT("alice_".to_owned()));
+        let elsewhere = Context { network: "other".to_owned(), ..ctx.to_owned() };
+        assert_eq!(logins.get(&ctx), Some("alice_"));
+        assert_eq!(logins.get(&elsewhere), None);
+    }
+
+    #[test]
+    fn forgets_users_who_leave() {
+        let mut logins = Logins::new();
+        let (a, b) = (Context::mock("#a", "Alice"), Context::mock("#b", "Alice"));
+        logins.update(&a, &JOIN("#a".to_owned(), Some("alice_".to_owned()), None));
+        logins.update(&b, &JOIN("#b".to_owned(), None, None));
+        logins.update(&a, &PART("#a".to_owned(), None));
+        assert_eq!(logins.get(&b), Some("alice_"));
+        let op = Context::mock("#b", "Bob");
+        logins.update(&op, &KICK("#b".to_owned(), "Alice".to_owned(), None));
+        assert_eq!(logins.get(&b), None);
+    }
+
+    #[test]
+    fn trusts_tags_once_acknowledged() {
+        let mut logins = Logins::new();
+        let ctx = Context::mock("#site19", "Alice");
+        logins.update(&ctx, &ACCOUNT("alice_".to_owned()));
+        assert_eq!(logins.get(&ctx), Some("alice_"));
+        let ack = CAP(
+            Some("*".to_owned()), CapSubCommand::ACK, None, Some("account-tag chghost".to_owned())
+        );
+        logins.acknowledge(&ctx.network, &ack);
+        assert_eq!(logins.get(&ctx), None);
+        let tagged = Context { account: Some("alice_".to_owned()), ..ctx };
+        assert_eq!(logins.get(&tagged), Some("alice_"));
+    }
+}
@@ -22,8 +22,12 @@ pub trait Diff<K: Clone + Eq + Hash + Send + Sync + 'static> {
     }
 
     fn diff(&mut self, cli: &Client) -> IO<()> {
-        let old = self.cache();
         let new = self.refresh(cli)?;
+        self.apply(new)
+    }
+
+    fn apply(&mut self, new: HashSet<K>) -> IO<()> {
+        let old = self.cache();
         for added in new.difference(&old) {
             self.send(added.clone(), true)?;
         }
@@ -34,3 +38,39 @@ pub trait Diff<K: Clone + Eq + Hash + Send + Sync + 'static> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestDiff(DiffSender<u32>, HashSet<u32>);
+
+    impl Diff<u32> for TestDiff {
+        fn new(sender: DiffSender<u32>) -> Self {
+            TestDiff(sender, HashSet::new())
+        }
+        fn cache(&self) -> &HashSet<u32> {
+            &self.1
+        }
+        fn send(&self, k: u32, v: bool) -> DiffResult<u32> {
+            self.0.send((k, v))
+        }
+        fn refresh(&self, _: &Client) -> IO<HashSet<u32>> {
+            Ok(self.1.clone())
+        }
+        fn update(&mut self, new: HashSet<u32>) {
+            self.1 = new;
+        }
+    }
+
+    #[test]
+    fn sends_changes() {
+        let (mut diff, receiver) = TestDiff::build();
+        diff.update(vec![1, 2].into_iter().collect());
+        diff.apply(vec![2, 3].into_iter().collect()).unwrap();
+        let mut sent: Vec<(u32, bool)> = receiver.try_iter().collect();
+        sent.sort();
+        assert_eq!(sent, vec![(1, false), (3, true)]);
+        assert_eq!(diff.cache(), &vec![2, 3].into_iter().collect());
+    }
+}
@@ -1,6 +1,7 @@
 use diesel::prelude::*;
 use diesel::query_dsl::RunQueryDsl;
 use reqwest::Client;
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
@@ -20,24 +21,43 @@ pub use self::diff::DiffReceiver;
 
 use self::bans::BansDiff;
 use self::diff::Diff;
+use self::pages::PagesDiff;
 use self::titles::TitlesDiff;
 
 pub fn spawn(pool: Pool, db: &mut Db) -> IO<()> {
     thread("attributions", pool.clone(), attributions::update);
-    thread("pages", pool.clone(), pages::update);
 
+    let index = db.index.clone();
+    let (mut pages, pages_r) = PagesDiff::build();
+    pages.update(pages::stored(&pool.get()?)?);
+    db.pages_r = Some(pages_r);
+    thread("pages", pool.clone(), move |cli, conn, wiki| {
+        let titles = pages::update(cli, conn, wiki, &index)?;
+        // An empty page table means this is the first sync, not thousands of new pages.
+        if pages.cache().is_empty() {
+            pages.update(titles);
+            Ok(())
+        } else {
+            pages.apply(titles)
+        }
+    });
+
+    // If a refresh fails, the first diff its thread runs sends everything as new instead.
     let (mut bans, bans_r) = BansDiff::build();
-    bans.update(bans.refresh(&db.client)?);
+    bans.refresh(&db.client).map(|x| bans.update(x)).log(trace!());
     db.bans   = bans.cache().clone().into_iter().collect();
     db.bans_r = Some(bans_r);
     thread("bans", pool.clone(), move |cli,_,_| bans.diff(cli));
 
     let (mut titles, titles_r) = TitlesDiff::build();
-    titles.update(titles.refresh(&db.client)?);
-    db.titles   = titles.cache().clone().into_iter().collect();
+    titles.refresh(&db.client).map(|x| titles.update(x)).log(trace!());
+    db.titles   = Arc::new(titles.cache().clone().into_iter().collect());
     db.titles_r = Some(titles_r);
     thread("titles", pool, move |cli,_,_| titles.diff(cli));
 
+    for (k, v) in db.titles.iter() {
+        db.index.set_subtitle(k, Some(v));
+    }
     Ok(())
 }
 
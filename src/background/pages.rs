@@ -1,15 +1,47 @@
 use diesel::prelude::*;
 use diesel::query_dsl::RunQueryDsl;
+use hashbrown::HashSet;
 use reqwest::Client;
 use std::time::SystemTime;
 
 use crate::IO;
-use crate::db::{Conn, upsert};
+use crate::db::{Conn, PageIndex, upsert};
 use crate::wikidot::Wikidot;
 use crate::db::{attribution, page, tag};
+use super::diff::{Diff, DiffResult, DiffSender};
 
+pub struct PagesDiff {
+    sender: DiffSender<String>,
+    pages:  HashSet<String>,
+    wiki:   Wikidot
+}
+
+impl Diff<String> for PagesDiff {
+    fn new(sender: DiffSender<String>) -> Self {
+        Self { sender, pages: HashSet::new(), wiki: Wikidot::new() }
+    }
+    fn cache(&self) -> &HashSet<String> {
+        &self.pages
+    }
+    fn refresh(&self, cli: &Client) -> IO<HashSet<String>> {
+        Ok(self.wiki.list(cli)?.into_iter().collect())
+    }
+    fn send(&self, k: String, v: bool) -> DiffResult<String> {
+        self.sender.send((k, v))
+    }
+    fn update(&mut self, pages: HashSet<String>) {
+        self.pages = pages;
+    }
+}
+
+/// Names of every page already in the page table.
+pub fn stored(conn: &Conn) -> IO<HashSet<String>> {
+    Ok(page::table.select(page::id).load::<String>(conn)?.into_iter().collect())
+}
 
-pub fn update(cli: &Client, conn: &Conn, wiki: &Wikidot) -> IO<()> {
+/// Syncs the page table with the wiki and returns the names of every page on it.
+pub fn update(cli: &Client, conn: &Conn, wiki: &Wikidot, index: &PageIndex) 
+-> IO<HashSet<String>> {
     let updated = SystemTime::now();
     let titles = wiki.list(cli)?;
     for chunk in titles.chunks(5000) {
@@ -44,5 +76,6 @@ pub fn update(cli: &Client, conn: &Conn, wiki: &Wikidot) -> IO<()> {
     diesel::delete(
         attribution::table.filter(attribution::page_id.ne_all(page::table.select(page::id)))
     ).execute(conn)?;
-    Ok(())
+    index.rebuild(conn)?;
+    Ok(titles.into_iter().collect())
 }
@@ -0,0 +1,239 @@
+use hashbrown::HashMap;
+use std::cmp;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use crate::Context;
+use crate::args;
+use crate::db::scoped;
+use crate::output::{MAX_LINES, MORE, Response};
+use crate::text::{Format, Text};
+
+const CHARACTER_LIMIT: usize = 400;
+const EXPIRY: Duration = Duration::from_secs(60 * 10);
+pub const MAX_RANGE: usize = 5;
+
+/// Each user's latest choices in each channel, so nobody can pick from someone else's results.
+/// Channels are `scoped` by network.
+#[derive(Debug, Default)]
+pub struct ChoiceMap(HashMap<(String, String), (Choices, Instant)>);
+
+impl ChoiceMap {
+    #[inline]
+    pub fn new() -> Self {
+        ChoiceMap(HashMap::new())
+    }
+
+    pub fn insert(&mut self, ctx: &Context, choices: Choices) {
+        self.0.retain(|_, (_, time)| time.elapsed() < EXPIRY);
+        self.0.insert(key(ctx), (choices, Instant::now()));
+    }
+
+    pub fn get_mut(&mut self, ctx: &Context) -> Option<&mut Choices> {
+        let (choices, time) = self.0.get_mut(&key(ctx))?;
+        if time.elapsed() < EXPIRY { Some(choices) } else { None }
+    }
+}
+
+/// The lines of each user's latest long response that didn't fit, waiting for `.more`.
+#[derive(Debug, Default)]
+pub struct Held(HashMap<(String, String), (Vec<Response>, Instant)>);
+
+impl Held {
+    #[inline]
+    pub fn new() -> Self {
+        Held(HashMap::new())
+    }
+
+    pub fn insert(&mut self, ctx: &Context, lines: Vec<Response>) {
+        self.0.retain(|_, (_, time)| time.elapsed() < EXPIRY);
+        self.0.insert(key(ctx), (lines, Instant::now()));
+    }
+
+    pub fn remove(&mut self, ctx: &Context) {
+        self.0.remove(&key(ctx));
+    }
+
+    /// Takes the next few lines. The last one is marked if there are still more after it.
+    pub fn next(&mut self, ctx: &Context) -> Vec<Response> {
+        let key = key(ctx);
+        let lines = match self.0.get_mut(&key) {
+            Some((lines, time)) if time.elapsed() < EXPIRY => lines,
+            _                                              => return Vec::new()
+        };
+        let rest = lines.split_off(cmp::min(lines.len(), MAX_LINES));
+        let mut page = mem::replace(lines, rest);
+        if lines.is_empty() {
+            self.0.remove(&key);
+        } else if let Some(last) = page.last_mut() {
+            last.push_str(MORE);
+        }
+        page
+    }
+}
+
+#[inline]
+fn key(ctx: &Context) -> (String, String) {
+    (scoped(&ctx.network, &ctx.channel), ctx.user.to_owned())
+}
+
+/// Parses a 1-indexed option number like `3` or an inclusive range like `2-4`.
+pub fn parse_range(s: &str) -> Option<(usize, usize)> {
+    let (from, to) = match s.find('-') {
+        None    => (s, s),
+        Some(i) => (&s[..i], &s[i+1..])
+    };
+    let from = from.parse().ok()?;
+    let to = to.parse().ok()?;
+    if from == 0 || from > to { None } else { Some((from, to)) }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Choices {
+    cmd:     String,
+    options: Vec<String>,
+    shown:   usize,
+    /// How many matches there were in all, which can be more than there are options.
+    total:   i64
+}
+
+impl Choices {
+    #[inline]
+    pub fn new(cmd: &str, options: Vec<String>) -> Self {
+        Self { cmd: cmd.to_owned(), options, shown: 0, total: 0 }
+    }
+
+    /// Shows the total after the first page of options.
+    pub fn with_total(mut self, total: i64) -> Self {
+        self.total = total;
+        self
+    }
+
+    /// The command to run for a 1-indexed option. Without a command, options are run as-is.
+    pub fn get(&self, i: usize) -> Option<String> {
+        let option = self.options.get(i.checked_sub(1)?)?;
+        if self.cmd.is_empty() {
+            Some(option.to_owned())
+        } else {
+            Some(format!("{} {}", self.cmd, args::quote(option)))
+        }
+    }
+
+    /// The commands to run for an inclusive range of 1-indexed options.
+    pub fn range(&self, from: usize, to: usize) -> Option<Vec<String>> {
+        (from..=to).map(|i| self.get(i)).collect()
+    }
+
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.options.len() - self.shown
+    }
+
+    /// Lists as many of the options that haven't been shown yet as will fit in a message.
+    pub fn next(&mut self) -> Option<Text> {
+        if self.remaining() == 0 {
+            return None
+        }
+        let start = self.shown;
+        let total = if start == 0 && self.total > 0 {
+            format!(" ({} total)", self.total)
+        } else {
+            String::new()
+        };
+        let mut text = Text::new().plain(if start == 0 { "Did you mean:" } else { "Or:" });
+        for (i, option) in self.options.iter().enumerate().skip(start) {
+            if i > start {
+                if text.width(Format::Irc) + option.len() + total.len() + 7 > CHARACTER_LIMIT {
+                    break
+                }
+                text.push_str(",");
+            }
+            text = text.plain(" ").bold(format!("{}.", i + 1)).plain(" ").plain(option);
+            self.shown = i + 1;
+        }
+        Some(text.plain(total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::Response::Reply;
+    use crate::util::own;
+
+    #[test]
+    fn pages_through_options() {
+        let options: Vec<String> = (0..100).map(|i| format!("Option {}", i)).collect();
+        let mut choices = Choices::new("s", options);
+        let mut pages = 0;
+        while let Some(text) = choices.next() {
+            assert!(text.width(Format::Irc) <= CHARACTER_LIMIT);
+            pages += 1;
+        }
+        assert!(pages > 1);
+        assert_eq!(choices.remaining(), 0);
+        let options = (0..100).map(|i| format!("Option {}", i)).collect();
+        let first = Choices::new("s", options).with_total(1000).next().unwrap();
+        assert!(first.width(Format::Irc) <= CHARACTER_LIMIT);
+        assert!(first.render(Format::Irc).ends_with(" (1000 total)"));
+        assert_eq!(choices.get(100), Some("s \"Option 99\"".to_owned()));
+    }
+
+    #[test]
+    fn shows_long_options() {
+        let long = "x".repeat(CHARACTER_LIMIT);
+        let mut choices = Choices::new("s", own(&[long.as_str(), "y"]));
+        assert!(choices.next().is_some());
+        let next = choices.next().map(|x| x.render(Format::Irc));
+        assert_eq!(next, Some("Or: \x022.\x02 y".to_owned()));
+        assert_eq!(choices.next(), None);
+    }
+
+    #[test]
+    fn rejects_out_of_range() {
+        let choices = Choices::new("s", own(&["a"]));
+        assert_eq!(choices.get(0), None);
+        assert_eq!(choices.get(2), None);
+        assert_eq!(choices.range(1, 2), None);
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("3"), Some((3, 3)));
+        assert_eq!(parse_range("2-4"), Some((2, 4)));
+        assert_eq!(parse_range("0"), None);
+        assert_eq!(parse_range("4-2"), None);
+        assert_eq!(parse_range("2-"), None);
+        assert_eq!(parse_range("x"), None);
+    }
+
+    #[test]
+    fn keeps_users_apart() {
+        let mut map = ChoiceMap::new();
+        map.insert(&Context::mock("#site19", "Alice"), Choices::new("s", own(&["a", "b"])));
+        map.insert(&Context::mock("#site19", "Bob"), Choices::new("w", own(&["c"])));
+        let alice = map.get_mut(&Context::mock("#site19", "alice")).unwrap();
+        assert_eq!(alice.range(1, 2), Some(own(&["s a", "s b"])));
+        let bob = map.get_mut(&Context::mock("#site19", "Bob")).unwrap();
+        assert_eq!(bob.get(1), Some("w c".to_owned()));
+        assert!(map.get_mut(&Context::mock("#site17", "Alice")).is_none());
+        let alice = Context::mock("#site19", "Alice");
+        assert!(map.get_mut(&Context { network: "synirc".to_owned(), ..alice }).is_none());
+    }
+
+    #[test]
+    fn pages_through_held_lines() {
+        let mut held = Held::new();
+        let ctx = Context::mock("#site19", "Alice");
+        let lines: Vec<Response> = (0..MAX_LINES + 1)
+            .map(|i| Reply(i.to_string().into()))
+            .collect();
+        held.insert(&ctx, lines);
+        let page = held.next(&ctx);
+        assert_eq!(page.len(), MAX_LINES);
+        assert!(page[MAX_LINES - 1].text().ends_with(MORE));
+        assert!(held.next(&Context::mock("#site19", "Bob")).is_empty());
+        assert_eq!(held.next(&ctx), vec![Reply(MAX_LINES.to_string().into())]);
+        assert!(held.next(&ctx).is_empty());
+    }
+}
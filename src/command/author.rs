@@ -1,40 +1,62 @@
-use getopts::{Matches, Options};
+#![allow(proc_macro_derive_resolution_fallback)]
+
+use diesel::sql_types::{BigInt, Text as SqlText};
 use std::borrow::ToOwned;
 
 use super::*;
 use crate::db::{Conn, Page, attribution, page, pages, tag};
 use crate::util;
 
-pub struct Author {
-    opts: Options
-}
+pub struct Author;
 
 impl Command for Author {
     fn cmds(&self) -> Vec<String> {
         abbrev("author")
     }
-    fn usage(&self) -> String { "[<author>] [-t <tag>] [-t <another>] [-< <before MM-DD-YYYY>] [-> <after MM-DD-YYYY>] [-e <exclude>] [-e <another>]".to_owned() }
-    fn fits(&self, _: usize) -> bool { true }
+    fn spec(&self) -> Spec { pages::options(Spec::new().optional("author", Kind::Word)) }
     fn auth(&self) -> Auth { Anyone }
+    fn cost(&self) -> u32 { NETWORK_COST }
+    fn description(&self) -> String {
+        "Summarizes an author's pages, total rating and ranking among other authors.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> { own(&["author Jabyrwock", "author Jabyrwock -t tale"]) }
 
-    fn run(&mut self, args: &[&str], ctx: &Context, db: &mut Db) -> Outcome {
-        let mut opts = self.opts.parse(args)?;
-        let result = match opts.free.split_off(0).as_slice() {
-            []           => self.tally(&ctx.nick, &opts, db),
-            [author_pat] => self.tally(author_pat, &opts, db),
-            _            => Err(InvalidArgs)
-        }?;
-        Ok(vec![Reply(result)])
+    fn run(&mut self, args: &Args, ctx: &Context, db: &mut Db) -> Outcome {
+        let author_pat = args.get("author").unwrap_or(&ctx.nick);
+        let tally = self.tally(author_pat, args, db)?;
+        let recent = db.wiki.rate(&tally.latest, &db.client).ok_or(NoResults)?;
+        Ok(vec![Reply(tally.finish(recent))])
+    }
+    fn spawn(&mut self, args: &Args, ctx: &Context, db: &Db) -> Option<Job<Outcome>> {
+        let author_pat = args.get("author").unwrap_or(&ctx.nick);
+        let tally = match self.tally(author_pat, args, db) {
+            Err(e)    => return Some(done(Err(e))),
+            Ok(tally) => tally
+        };
+        let (wiki, cli) = (db.wiki.clone(), db.client.clone());
+        Some(Box::new(move || {
+            let recent = wiki.rate(&tally.latest, &cli).ok_or(NoResults)?;
+            Ok(vec![Reply(tally.finish(recent))])
+        }))
     }
 }
 
-impl Author {
-    #[inline]
-    pub fn new() -> Self {
-        Self { opts: pages::options() }
+/// Everything about an author except their latest page's live rating, which has to be fetched
+/// from the wiki.
+struct Tally {
+    head:   Text,
+    latest: String,
+    tail:   Text
+}
+
+impl Tally {
+    fn finish(self, recent: i64) -> Text {
+        self.head.bold(util::rating(recent)).append(self.tail)
     }
-    
-    fn tally(&self, author_pat: &str, opts: &Matches, db: &mut Db) -> Result<String, Error> {
+}
+
+impl Author {
+    fn tally(&self, author_pat: &str, opts: &Args, db: &Db) -> Result<Tally, Error> {
         let conn = db.conn()?;
         let mut authors = page::table
             .filter(page::created_by.ilike(author_pat))
@@ -45,67 +67,68 @@ impl Author {
             .select(attribution::user)
             .load(&conn)?
         );
+        authors.sort();
         authors.dedup();
         let author = match authors.as_slice() {
             []       => Err(NoResults),
             [author] => Ok(author),
             _        => Err(Ambiguous(authors.len() as i64, authors))
         }?;
-        let scps = Self::tagged("scp", author, opts, &conn)?;
-        let tales = Self::tagged("tale", author, opts, &conn)?;
-        let gois = Self::tagged("goi-format", author, opts, &conn)?;
-        let hubs = Self::tagged("hub", author, opts, &conn)?;
-        let art = Self::tagged("artwork", author, opts, &conn)?;
-        let scps_len = scps.len();
-        let tales_len = tales.len();
-        let gois_len = gois.len();
-        let hubs_len = hubs.len();
-        let art_len = art.len();
-
-        let mut all = [scps, tales, gois, hubs, art].concat();
-        all.sort();
-        all.dedup();
-
+        let kinds = [
+            (Self::tagged("scp", author, opts, &conn)?.len(), "SCP article"),
+            (Self::tagged("tale", author, opts, &conn)?.len(), "tale"),
+            (Self::tagged("goi-format", author, opts, &conn)?.len(), "GOI article"),
+            (Self::tagged("hub", author, opts, &conn)?.len(), "hub"),
+            (Self::tagged("artwork", author, opts, &conn)?.len(), "artwork page")
+        ];
+
+        let all: Vec<Page> = pages::filter_by(author, pages::filter(opts, page::table)?)
+            .load(&conn)?;
         let all_len = all.len();
 
         let mut votes = 0;
-        let mut latest = all.first().ok_or(NoResults)?.clone();
+        let mut first = all.first().ok_or(NoResults)?.clone();
+        let mut latest = first.clone();
 
         for page in all {
             votes += i64::from(page.rating);
-            if page.created_at > latest.created_at {
+            if page.created_at < first.created_at {
+                first = page;
+            } else if page.created_at > latest.created_at {
                 latest = page;
             }
         }
 
-        let recent = db.wiki.rate(&latest.id, &db.client).ok_or(NoResults)?;
-
-        let mut s = "\x02".to_owned();
-        s.push_str(author);
-        s.push_str("\x02 has \x02");
-        s.push_str(&all_len.to_string());
-        s.push_str("\x02 pages (");
-        
-        let mut comma = count(false, &mut s, scps_len, "SCP article");
-        comma = count(comma, &mut s, tales_len, "tale");
-        comma = count(comma, &mut s, gois_len, "GOI article");
-        comma = count(comma, &mut s, hubs_len, "hub");
-        count(comma, &mut s, art_len, "artwork page");
-
-        s.push_str("). They have \x02");
-        s.push_str(&votes.to_string());
-        s.push_str("\x02 net votes with an average of \x02");
-        s.push_str(&util::rating(votes / all_len as i64));
-        s.push_str("\x02. Their latest page is \x02");
-        s.push_str(&db.title(&latest));
-        s.push_str("\x02 at \x02");
-        s.push_str(&util::rating(recent));
-        s.push_str("\x02.");
-
-        Ok(s)
+        let rank = rank(author, &conn)?.ok_or(NoResults)?;
+
+        let mut s = Text::new().bold(author).plain(" has ").append(count(all_len, "page"));
+        let kinds = counts(&kinds);
+        if !kinds.is_empty() {
+            s = s.plain(" (").append(kinds).plain(")");
+        }
+
+        s = s
+            .plain(". They have ").bold(votes)
+            .plain(" net votes with an average of ").bold(util::rating(votes / all_len as i64))
+            .plain(", ranking ").bold(format!("#{}", rank.by_rating))
+            .plain(" by total rating and ").bold(format!("#{}", rank.by_pages))
+            .plain(" by page count out of ").bold(rank.authors)
+            .plain(" authors. Their first page is ").bold(db.title(&first))
+            .plain(format!(" ({} ago) and their latest is ", util::ago(first.created_at)))
+            .bold(db.title(&latest))
+            .plain(" at ");
+
+        let mut tail = Text::new().plain(".");
+        if let Some(author_page) = Self::author_page(author, &conn)? {
+            tail = tail
+                .plain(" Author page: ")
+                .link(format!("http://{}/{}", db.wiki.root, author_page.id));
+        }
+
+        Ok(Tally { head: s, latest: latest.id, tail })
     }
 
-    fn tagged(tag: &str, author: &str, opts: &Matches, conn: &Conn) -> Result<Vec<Page>, Error> {
+    fn tagged(tag: &str, author: &str, opts: &Args, conn: &Conn) -> Result<Vec<Page>, Error> {
         Ok(pages::filter_by(author, pages::filter(opts, page::table
                 .filter(page::id.eq_any(
                     tag::table
@@ -115,38 +138,110 @@ impl Author {
             )?).load(conn)?
         )
     }
-}
 
-fn count(comma: bool, s: &mut String, size: usize, name: &str) -> bool {
-    if size == 0 {
-        comma
-    } else {
-        if comma {
-            s.push_str(", ");
-        }
-        s.push_str("\x02");
-        s.push_str(&size.to_string());
-        s.push_str("\x02 ");
-        s.push_str(name);
-        if size != 1 {
-            s.push_str("s");
+    /// Finds a page tagged `author` that names the author in its title, 
+    /// or failing that, one that the author created.
+    fn author_page(author: &str, conn: &Conn) -> Result<Option<Page>, Error> {
+        let author_pages = || page::table
+            .filter(page::id.eq_any(
+                tag::table
+                    .select(tag::page_id)
+                    .filter(tag::name.eq("author"))
+            ))
+            .order(page::created_at.asc());
+        let named = author_pages()
+            .filter(page::title.ilike(format!("%{}%", author)))
+            .first(conn)
+            .optional()?;
+        if named.is_some() {
+            return Ok(named)
         }
-        true
+        Ok(author_pages()
+            .filter(page::created_by.eq(author))
+            .first(conn)
+            .optional()?
+        )
     }
 }
 
+/// Totals every author's pages, counting a page once per author whether they created it or were
+/// attributed it, then ranks them all by total rating and by page count.
+const RANK: &str = "
+    WITH credit AS (
+        SELECT created_by AS author, id AS page_id FROM page
+        UNION
+        SELECT attribution.\"user\", attribution.page_id
+        FROM attribution JOIN page ON page.id = attribution.page_id
+    ), totals AS (
+        SELECT author, SUM(page.rating) AS rating, COUNT(*) AS pages
+        FROM credit JOIN page ON page.id = credit.page_id
+        GROUP BY author
+    ), ranked AS (
+        SELECT author,
+            RANK() OVER (ORDER BY rating DESC) AS by_rating,
+            RANK() OVER (ORDER BY pages DESC) AS by_pages,
+            COUNT(*) OVER () AS authors
+        FROM totals
+    )
+    SELECT by_rating, by_pages, authors FROM ranked WHERE author = $1
+";
+
+#[derive(QueryableByName)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Rank {
+    #[sql_type = "BigInt"]
+    by_rating: i64,
+    #[sql_type = "BigInt"]
+    by_pages:  i64,
+    #[sql_type = "BigInt"]
+    authors:   i64
+}
+
+/// Ranks an author against everyone who has created or been attributed a page.
+fn rank(author: &str, conn: &Conn) -> Result<Option<Rank>, Error> {
+    Ok(diesel::sql_query(RANK)
+        .bind::<SqlText, _>(author)
+        .get_result(conn)
+        .optional()?
+    )
+}
+
+fn count(size: usize, name: &str) -> Text {
+    Text::new().bold(size).plain(format!(" {}{}", name, if size == 1 { "" } else { "s" }))
+}
+
+/// Lists how many pages there are of each kind, leaving out kinds with none.
+fn counts(kinds: &[(usize, &str)]) -> Text {
+    Text::list(kinds.iter().filter(|(size, _)| *size > 0).map(|&(size, name)| count(size, name)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::text::Format;
 
     #[test]
-    fn counts() {
-        let mut s = String::new();
-        let mut comma = count(false, &mut s, 0, "SCP article");
-        comma = count(comma, &mut s, 1, "tale");
-        comma = count(comma, &mut s, 0, "GOI article");
-        comma = count(comma, &mut s, 5, "hub");
-        count(comma, &mut s, 8, "artwork page");
-        assert_eq!(s, "\u{2}1\u{2} tale, \u{2}5\u{2} hubs, \u{2}8\u{2} artwork pages");
+    fn counts_kinds() {
+        let kinds = [
+            (0, "SCP article"),
+            (1, "tale"),
+            (0, "GOI article"),
+            (5, "hub"),
+            (8, "artwork page")
+        ];
+        assert_eq!(
+            counts(&kinds).render(Format::Irc),
+            "\u{2}1\u{2} tale, \u{2}5\u{2} hubs, \u{2}8\u{2} artwork pages"
+        );
+    }
+
+    #[test] #[ignore]
+    fn ranks() {
+        let db = Db::default();
+        let conn = db.conn().unwrap();
+        let rank = rank("Jabyrwock", &conn).unwrap().unwrap();
+        assert!(rank.by_rating >= 1 && rank.by_rating <= rank.authors);
+        assert!(rank.by_pages >= 1 && rank.by_pages <= rank.authors);
+        assert_eq!(rank("no-such-author-exists", &conn).unwrap(), None);
     }
 }
@@ -12,14 +12,15 @@ impl Command for Choose {
     fn cmds(&self) -> Vec<String> {
         abbrev("choose")
     }
-    fn usage(&self) -> String { "<choices, separated, by, commas>".to_owned() }
-    fn fits(&self, size: usize) -> bool { size >= 1 }
+    fn spec(&self) -> Spec { Spec::new().rest("choices, separated, by, commas") }
     fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String { "Picks one of several options at random.".to_owned() }
+    fn examples(&self) -> Vec<String> { own(&["ch pizza, tacos, sushi"]) }
 
-    fn run(&mut self, args: &[&str], _: &Context, _: &mut Db) -> Outcome {
-        let choices = args.join(" ");
+    fn run(&mut self, args: &Args, _: &Context, _: &mut Db) -> Outcome {
+        let choices = args.text("choices, separated, by, commas");
         let opts: Vec<&str> = choices.split(',').map(str::trim).collect();
-        Ok(vec![Reply(opts[self.rng.gen_range(0, opts.len())].to_owned())])
+        Ok(vec![Reply(opts[self.rng.gen_range(0, opts.len())].into())])
     }
 }
 
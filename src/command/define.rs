@@ -6,6 +6,7 @@ use select::predicate::{Class, Name};
 use super::*;
 use crate::util;
 
+#[derive(Clone)]
 pub struct Define {
     spaced: Regex,
     parens: Regex
@@ -15,12 +16,18 @@ impl Command for Define {
     fn cmds(&self) -> Vec<String> {
         abbrev("define")
     }
-    fn usage(&self) -> String { "<query>".to_owned() }
-    fn fits(&self, size: usize) -> bool { size >= 1 }
+    fn spec(&self) -> Spec { Spec::new().rest("query") }
     fn auth(&self) -> Auth { Anyone }
+    fn cost(&self) -> u32 { NETWORK_COST }
+    fn description(&self) -> String { "Looks up the definition of a word.".to_owned() }
+    fn examples(&self) -> Vec<String> { own(&["define gulch"]) }
 
-    fn run(&mut self, args: &[&str], _: &Context, db: &mut Db) -> Outcome {
-        Ok(vec![Reply(self.search(&args.join(" "), &db.client)?)])
+    fn run(&mut self, args: &Args, _: &Context, db: &mut Db) -> Outcome {
+        Ok(vec![Reply(self.search(args.text("query"), &db.client)?)])
+    }
+    fn spawn(&mut self, args: &Args, _: &Context, db: &Db) -> Option<Job<Outcome>> {
+        let (define, query, cli) = (self.clone(), args.text("query").to_owned(), db.client.clone());
+        Some(Box::new(move || Ok(vec![Reply(define.search(&query, &cli)?)])))
     }
 }
 
@@ -47,7 +54,7 @@ impl Define {
     }
 
         
-    fn search(&self, query: &str, cli: &reqwest::Client) -> Result<String, Error> {
+    fn search(&self, query: &str, cli: &reqwest::Client) -> Result<Text, Error> {
         let page = Document::from_read(
             cli.get(&format!("http://ninjawords.com/{}", util::encode(query))).send()?
         )?;
@@ -57,7 +64,7 @@ impl Define {
             .ok_or_else(||ParseErr(err_msg("Missing title-word")))?;
         Ok(self.parse(word.text().trim(), &page))
     }
-    fn parse(&self, word: &str, doc: &Document) -> String {
+    fn parse(&self, word: &str, doc: &Document) -> Text {
         let mut defs = MultiMap::new();
         let mut article = String::new();
         for node in doc.find(Name("dd")) {
@@ -75,25 +82,15 @@ impl Define {
     }
 }
 
-fn stringify(word: &str, defs: &MultiMap<String, String>) -> String {
-    let mut s = String::new();
-    s.push_str("\x02");
-    s.push_str(word);
-    s.push_str(":\x02");
+fn stringify(word: &str, defs: &MultiMap<String, String>) -> Text {
+    let mut text = Text::new().bold(format!("{}:", word));
     for (k, vs) in defs.iter_all() {
-        s.push_str(" \x1d(");
-        s.push_str(k);
-        s.push_str(")\x1d");
-        let mut i = 1;
-        for v in vs {
-            s.push_str(" \x02");
-            s.push_str(&i.to_string());
-            s.push_str(".\x02 ");
-            s.push_str(v);
-            i += 1;
+        text = text.plain(" ").italic(format!("({})", k));
+        for (i, v) in vs.iter().enumerate() {
+            text = text.plain(" ").bold(format!("{}.", i + 1)).plain(" ").plain(v);
         }
     }
-    s
+    text
 }
 
 
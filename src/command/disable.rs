@@ -1,5 +1,10 @@
 use super::*;
-use crate::db::{Silence, silence};
+use crate::db::{GLOBAL, Silence, silence, upsert};
+use crate::util;
+
+const DURATION: Kind = Kind::Check(
+    "days, hours and minutes like 1d4h30m, up to a year", |x| util::parse_duration(x).is_some()
+);
 
 pub struct Disable {
     enable: bool,
@@ -10,26 +15,55 @@ impl Command for Disable {
     fn cmds(&self) -> Vec<String> {
         if self.enable { own(&["enable"]) } else { own(&["disable"]) }
     }
-    fn usage(&self) -> String { "<command>".to_owned() }
-    fn fits(&self, size: usize) -> bool { size == 1 }
+    fn spec(&self) -> Spec {
+        let spec = Spec::new().param("command", Kind::Word);
+        let spec = if self.enable { spec } else { spec.optional("duration", DURATION) };
+        spec.flag("g", "global")
+    }
     fn auth(&self) -> Auth { HalfOp }
+    fn auth_for(&self, args: &Args) -> Auth {
+        if args.opt_present("global") { Owner } else { Anyone }
+    }
+    fn description(&self) -> String {
+        if self.enable {
+            "Lets the bot respond to a disabled command in this channel again. With --global, \
+             undoes a disable everywhere on this network."
+        } else {
+            "Stops the bot from responding to a command in this channel, for a while if given a \
+             duration. With --global, disables it everywhere on this network, which only the \
+             owner can do."
+        }.to_owned()
+    }
+    fn examples(&self) -> Vec<String> {
+        if self.enable {
+            own(&["enable google", "enable -g google"])
+        } else {
+            own(&["disable google", "disable google 2h", "disable -g google"])
+        }
+    }
 
-    fn run(&mut self, args: &[&str], ctx: &Context, db: &mut Db) -> Outcome {
-        let cmd = args.join(" ").to_lowercase();
-        let canon = match cmd.chars().next() {
-            Some('!') => self.canons.get(&cmd[1..]),
-            Some('.') => self.canons.get(&cmd[1..]),
-            _         => self.canons.get(&cmd)
+    fn run(&mut self, args: &Args, ctx: &Context, db: &mut Db) -> Outcome {
+        let cmd = args.text("command").to_lowercase();
+        let canon = match self.canons.get(cmd.trim_start_matches(|c| c == '.' || c == '!')) {
+            Some(canon) => canon.to_owned(),
+            None        => return Ok(vec![Reply("I'm sorry, I don't know that command.".into())])
         };
-        match canon {
-            None        => Ok(vec![Reply("I'm sorry, I don't know that command.".to_owned())]),
-            Some(canon) => {
-                self.set_enabled(&canon, ctx, db)?;
-                Ok(vec![Action(
-                    format!("{}s .{}.", if self.enable { "enable" } else { "disable" }, canon)
-                )])
-            }
-        }
+        let global = args.opt_present("global");
+        let duration = args.get("duration");
+        let until = match duration {
+            None    => None,
+            Some(x) => Some(util::after(x).ok_or(InvalidArgs)?)
+        };
+        self.set_enabled(Silence {
+            network: ctx.network.to_owned(),
+            channel: if global { GLOBAL } else { &ctx.channel }.to_owned(),
+            command: canon.to_owned(),
+            until
+        }, db)?;
+        let verb = if self.enable { "enables" } else { "disables" };
+        let place = if global { " everywhere on this network" } else { "" };
+        let time = duration.map(|x| format!(" for {}", x)).unwrap_or_default();
+        Ok(vec![Action(format!("{} .{}{}{}.", verb, canon, place, time).into())])
     }
 }
 
@@ -39,20 +73,23 @@ impl Disable {
         Self { enable, canons }
     }
     
-    pub fn set_enabled(&self, cmd: &str, ctx: &Context, db: &mut Db) -> Result<(), Error> {
+    /// Disabling a command that's already disabled replaces the old end time.
+    pub fn set_enabled(&self, silence: Silence, db: &mut Db) -> Result<(), Error> {
         let conn = db.conn()?;
         if self.enable {
-            db.silences.remove(&ctx.channel, &cmd);
+            db.silences.remove_by(&silence);
             diesel::delete(silence::table
-                .filter(silence::channel.eq(&ctx.channel))
-                .filter(silence::command.eq(&cmd))
+                .filter(silence::network.eq(&silence.network))
+                .filter(silence::channel.eq(&silence.channel))
+                .filter(silence::command.eq(&silence.command))
             ).execute(&conn)?;
         } else {
-            let silence = Silence { channel: ctx.channel.to_owned(), command: cmd.to_owned() };
             diesel::insert_into(silence::table)
                 .values(&silence)
-                .on_conflict_do_nothing()
-            .execute(&conn)?;
+                .on_conflict((silence::network, silence::channel, silence::command))
+                .do_update()
+                .set(upsert(silence::until))
+                .execute(&conn)?;
             db.silences.insert(silence);
         }
         Ok(())
@@ -62,6 +99,7 @@ impl Disable {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::SystemTime;
     
     const CMD: &str = "x";
 
@@ -71,16 +109,16 @@ mod tests {
         Disable::new(enable, canons)
     }
     fn is_enabled(cmd: &str, db: &Db) -> bool {
-        !db.silences.contains(&Context::default().channel, cmd)
+        !db.silenced(&Context::default(), cmd)
     }
 
     #[test] #[ignore]
     fn disables() {
         let mut db = Db::default();
         let mut enable = new(true);
-        enable.run(&[CMD], &Context::default(), &mut db).unwrap();
+        enable.test(CMD, &Context::default(), &mut db).unwrap();
         let mut disable = new(false);
-        disable.run(&[CMD], &Context::default(), &mut db).unwrap();
+        disable.test(CMD, &Context::default(), &mut db).unwrap();
         assert!(!is_enabled(CMD, &db));
     }
 
@@ -88,9 +126,9 @@ mod tests {
     fn enables() {
         let mut db = Db::default();
         let mut disable = new(false);
-        disable.run(&[CMD], &Context::default(), &mut db).unwrap();
+        disable.test(CMD, &Context::default(), &mut db).unwrap();
         let mut enable = new(true);
-        enable.run(&[CMD], &Context::default(), &mut db).unwrap();
+        enable.test(CMD, &Context::default(), &mut db).unwrap();
         assert!(is_enabled(CMD, &db));
     }
 
@@ -98,7 +136,40 @@ mod tests {
     fn not_found() {
         let mut db = Db::default();
         let mut disable = new(false);
-        disable.run(&["y"], &Context::default(), &mut db).unwrap();
+        disable.test("y", &Context::default(), &mut db).unwrap();
         assert!(is_enabled("y", &db));
     }
+
+    #[test]
+    fn checks_durations() {
+        assert!(new(false).test_def("x soon").is_err());
+        assert!(new(true).test_def("x 2h").is_err());
+    }
+
+    #[test]
+    fn guards_global() {
+        let disable = new(false);
+        assert_eq!(disable.auth_for(&disable.spec().parse(&["-g", "x"]).unwrap()), Owner);
+        assert_eq!(disable.auth_for(&disable.spec().parse(&["x"]).unwrap()), Anyone);
+    }
+
+    #[test]
+    fn expires() {
+        let mut db = Db::default();
+        let channel = Context::default().channel;
+        let past = SystemTime::now() - std::time::Duration::from_secs(60);
+        let silence = Silence { channel, command: CMD.to_owned(), ..Silence::default() };
+        db.silences.insert(Silence { until: Some(past), ..silence.clone() });
+        assert!(is_enabled(CMD, &db));
+        db.silences.insert(Silence { channel: GLOBAL.to_owned(), ..silence });
+        assert!(!is_enabled(CMD, &db));
+    }
+
+    #[test]
+    fn keeps_networks_apart() {
+        let mut db = Db::default();
+        let network = "synirc".to_owned();
+        db.silences.insert(Silence { network, command: CMD.to_owned(), ..Silence::default() });
+        assert!(is_enabled(CMD, &db));
+    }
 }
@@ -0,0 +1,86 @@
+use super::*;
+use crate::db::{GLOBAL, Silence, scoped};
+use crate::util;
+
+pub struct Disabled;
+
+impl Command for Disabled {
+    fn cmds(&self) -> Vec<String> {
+        own(&["disabled"])
+    }
+    fn spec(&self) -> Spec { Spec::new() }
+    fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String {
+        "Lists the commands disabled in this channel and everywhere on this network, and how long \
+         they have left.".to_owned()
+    }
+
+    fn run(&mut self, _: &Args, ctx: &Context, db: &mut Db) -> Outcome {
+        let here = show(db.silences.in_channel(&scoped(&ctx.network, &ctx.channel)));
+        let everywhere = show(db.silences.in_channel(&scoped(&ctx.network, GLOBAL)));
+        let text = match (here.is_empty(), everywhere.is_empty()) {
+            (true, true)   => "Nothing is disabled here.".into(),
+            (false, true)  => Text::new().plain("Disabled here: ").append(here).plain("."),
+            (true, false)  => Text::new()
+                .plain("Disabled everywhere on this network: ")
+                .append(everywhere)
+                .plain("."),
+            (false, false) => Text::new()
+                .plain("Disabled here: ")
+                .append(here)
+                .plain(". Disabled everywhere on this network: ")
+                .append(everywhere)
+                .plain(".")
+        };
+        Ok(vec![Reply(text)])
+    }
+}
+
+fn show(mut silences: Vec<&Silence>) -> Text {
+    silences.retain(|x| x.active());
+    silences.sort_by(|x, y| x.command.cmp(&y.command));
+    Text::list(silences.into_iter().map(|x| {
+        let cmd = Text::new().bold(format!(".{}", x.command));
+        match x.until {
+            None        => cmd,
+            Some(until) => cmd.plain(format!(" ({} left)", util::until(until)))
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn silence(channel: &str, command: &str, until: Option<SystemTime>) -> Silence {
+        Silence {
+            channel: channel.to_owned(),
+            command: command.to_owned(),
+            until,
+            ..Silence::default()
+        }
+    }
+
+    #[test]
+    fn lists_nothing() {
+        assert_eq!(Disabled.test_def("").unwrap(), "Nothing is disabled here.");
+    }
+
+    #[test]
+    fn lists_silences() {
+        let mut db = Db::default();
+        let ctx = Context::default();
+        let hour = SystemTime::now() + Duration::from_secs(60 * 60 + 30);
+        let past = SystemTime::now() - Duration::from_secs(60);
+        db.silences.insert(silence(&ctx.channel, "google", Some(hour)));
+        db.silences.insert(silence(&ctx.channel, "seen", None));
+        db.silences.insert(silence(&ctx.channel, "tell", Some(past)));
+        db.silences.insert(silence(GLOBAL, "zyn", None));
+        assert_eq!(
+            Disabled.test("", &ctx, &mut db).unwrap(),
+            "Disabled here: \x02.google\x02 (1 hour left), \x02.seen\x02. \
+             Disabled everywhere on this network: \x02.zyn\x02."
+        );
+    }
+}
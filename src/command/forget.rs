@@ -7,14 +7,17 @@ impl Command for Forget {
     fn cmds(&self) -> Vec<String> {
         own(&[&"forget"])
     }
-    fn usage(&self) -> String { "<user>".to_owned() }
-    fn fits(&self, size: usize) -> bool { size == 1 }
+    fn spec(&self) -> Spec { Spec::new().param("user", Kind::Word) }
     fn auth(&self) -> Auth { Owner }
+    fn description(&self) -> String {
+        "Deletes everything the bot knows about a user, including tells to and from them.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> { own(&["forget Jabyrwock"]) }
 
-    fn run(&mut self, args: &[&str], _: &Context, db: &mut Db) -> Outcome {
-        let nick = args[0];
+    fn run(&mut self, args: &Args, _: &Context, db: &mut Db) -> Outcome {
+        let nick = args.text("user");
         delete_user(&nick, &db.conn()?)?;
-        Ok(vec![Action(format!("forgets {}.", nick))])
+        Ok(vec![Action(format!("forgets {}.", nick).into())])
     }
 }
 
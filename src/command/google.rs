@@ -3,6 +3,7 @@ use serde_json::Value;
 use super::*;
 use crate::{Api, util};
 
+#[derive(Clone)]
 pub struct Google {
     api: Api,
     img: bool
@@ -12,12 +13,26 @@ impl Command for Google {
     fn cmds(&self) -> Vec<String> {
         if self.img { own(&["gis"]) } else { abbrev("google") }
     }
-    fn usage(&self) -> String { "<query>".to_owned() }
-    fn fits(&self, size: usize) -> bool { size >= 1 }
+    fn spec(&self) -> Spec { Spec::new().rest("query") }
     fn auth(&self) -> Auth { Anyone }
+    fn cost(&self) -> u32 { NETWORK_COST }
+    fn description(&self) -> String {
+        if self.img {
+            "Searches Google Images and shows the top result."
+        } else {
+            "Searches Google and shows the top result."
+        }.to_owned()
+    }
+    fn examples(&self) -> Vec<String> {
+        if self.img { own(&["gis puma"]) } else { own(&["g puma"]) }
+    }
 
-    fn run(&mut self, args: &[&str], _: &Context, db: &mut Db) -> Outcome {
-        Ok(vec![Reply(self.search(&args.join(" "), &db.client)?)])
+    fn run(&mut self, args: &Args, _: &Context, db: &mut Db) -> Outcome {
+        Ok(vec![Reply(self.search(args.text("query"), &db.client)?)])
+    }
+    fn spawn(&mut self, args: &Args, _: &Context, db: &Db) -> Option<Job<Outcome>> {
+        let (google, query, cli) = (self.clone(), args.text("query").to_owned(), db.client.clone());
+        Some(Box::new(move || Ok(vec![Reply(google.search(&query, &cli)?)])))
     }
 }
 
@@ -29,7 +44,7 @@ impl Google {
         })
     }
     
-    fn parse(&self, json: &Value) -> Option<String> {
+    fn parse(&self, json: &Value) -> Option<Text> {
         let obj = json
             .as_object()?
             .get("items")?
@@ -39,15 +54,16 @@ impl Google {
         let get = |key| Some(obj.get(key)?.as_str()?.replace("\"", ""));
         let title = ellipses(&get("title")?);
         let link = get("link")?;
+        let text = Text::new().link(link).plain(" ").bold(title);
         if self.img {
-            Some(format!("{} \x02{}\x02", link, title))
+            Some(text)
         } else {    
             let snippet = ellipses(&get("snippet")?.replace("\n", ""));
-            Some(format!("{} \x02{}\x02: {}", link, title, snippet))
+            Some(text.plain(": ").plain(snippet))
         }
     }
 
-    fn search(&self, query: &str, cli: &reqwest::Client) -> Result<String, Error> {
+    fn search(&self, query: &str, cli: &reqwest::Client) -> Result<Text, Error> {
         let search_res = cli.get(&format!(
             "https://www.googleapis.com/customsearch/v1?key={}&cx={}&q={}&alt=json{}",
             self.api.key, self.api.user, util::encode(query), 
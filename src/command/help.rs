@@ -0,0 +1,111 @@
+use super::*;
+
+impl Commands {
+    /// Lists the commands the caller can use in the channel, leaving out disabled ones.
+    pub(super) fn list(&self, ctx: &Context, db: &Db, auth: Auth) -> Vec<Response> {
+        let mut names: Vec<&String> = self.canons
+            .iter()
+            .filter(|(cmd, canon)| cmd == canon && !db.silenced(ctx, canon))
+            .filter(|(cmd, _)| self.get(cmd).map_or(false, |x| {
+                db.permission(ctx, cmd, x.auth()) <= auth
+            }))
+            .map(|(cmd, _)| cmd)
+            .collect();
+        names.sort();
+        let names: Vec<&str> = names.into_iter().map(String::as_str).collect();
+        vec![Reply(Text::new()
+            .plain(format!("Commands you can use here: {}. ", names.join(", ")))
+            .plain("For details about one, use ")
+            .bold("help <command>")
+            .plain(".")
+        )]
+    }
+
+    /// Describes a command: what it does, its aliases, who can use it, and some examples.
+    pub(super) fn describe(&self, query: &str, ctx: &Context, db: &Db) -> Vec<Response> {
+        let query = query.trim_start_matches(|c| c == '.' || c == '!').to_lowercase();
+        let (canon, x) = match (self.canons.get(&query), self.get(&query)) {
+            (Some(canon), Some(x)) => (canon, x),
+            _                      => return vec![Reply(self.usage(&query))]
+        };
+        let mut aliases: Vec<&str> = self.canons
+            .iter()
+            .filter(|(cmd, c)| c == &canon && cmd != &canon)
+            .map(|(cmd, _)| cmd.as_str())
+            .collect();
+        aliases.sort_by_key(|x| (x.len(), *x));
+        let mut about = Text::new().bold(canon);
+        if !aliases.is_empty() {
+            about.push_str(&format!(" (also {})", aliases.join(", ")));
+        }
+        about.push_str(": ");
+        about.push_str(&x.description());
+        let needed = db.permission(ctx, canon, x.auth());
+        if needed > Anyone {
+            about.push_str(&format!(" Requires {}.", needed));
+        }
+
+        let mut responses = vec![Reply(about), Reply(self.usage(canon))];
+        let examples = x.examples();
+        if !examples.is_empty() {
+            let config = db.prefix(ctx);
+            let start = config.prefixes().first().cloned().unwrap_or("");
+            let examples = examples
+                .into_iter()
+                .map(|example| Text::new().bold(format!("{}{}", start, example)));
+            responses.push(Reply(Text::new().plain("Examples: ").append(Text::list(examples))));
+        }
+        responses
+    }
+
+    pub(super) fn get(&self, cmd: &str) -> Option<&dyn Command> {
+        let &key = self.keys.get(cmd)?;
+        self.stash.get(key).map(|x| &**x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Silence, establish_connection};
+
+    fn texts(responses: Vec<Response>) -> Vec<String> {
+        responses.into_iter().map(|x| x.text()).collect()
+    }
+
+    #[test]
+    fn lists_by_auth() {
+        let cmds = Commands::new(&establish_connection());
+        let db = Db::default();
+        let anyone = texts(cmds.list(&Context::default(), &db, Anyone)).join("");
+        assert!(anyone.contains("search"));
+        assert!(!anyone.contains("quit"));
+        let owner = texts(cmds.list(&Context::default(), &db, Owner)).join("");
+        assert!(owner.contains("quit"));
+    }
+
+    #[test]
+    fn hides_disabled() {
+        let cmds = Commands::new(&establish_connection());
+        let mut db = Db::default();
+        db.silences.insert(Silence {
+            channel: String::new(),
+            command: "search".to_owned(),
+            ..Silence::default()
+        });
+        let list = texts(cmds.list(&Context::default(), &db, Anyone)).join("");
+        assert!(!list.contains("search"));
+        assert!(list.contains("seen"));
+    }
+
+    #[test]
+    fn describes_commands() {
+        let cmds = Commands::new(&establish_connection());
+        let about = texts(cmds.describe(".s", &Context::default(), &Db::default()));
+        assert!(about[0].starts_with("\x02search\x02 (also s, sea, sear, searc): Searches"));
+        assert!(about[1].starts_with("Usage: \x02search\x02"));
+        assert!(about[2].starts_with("Examples: \x02!s tree -t scp\x02"));
+        let quit = texts(cmds.describe("quit", &Context::default(), &Db::default()));
+        assert_eq!(quit[0], "\x02quit\x02: Shuts down the bot. Requires owner.");
+    }
+}
@@ -6,12 +6,12 @@ impl Command for Hug {
     fn cmds(&self) -> Vec<String> {
         own(&["hug", "hugs", "hugme"])
     }
-    fn usage(&self) -> String { "".to_owned() }
-    fn fits(&self, size: usize) -> bool { size == 0 }
+    fn spec(&self) -> Spec { Spec::new() }
     fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String { "Gives you a hug.".to_owned() }
 
-    fn run(&mut self, _: &[&str], ctx: &Context, _: &mut Db) -> Outcome {
-        Ok(vec![Action(format!("hugs {}.", ctx.nick))])
+    fn run(&mut self, _: &Args, ctx: &Context, _: &mut Db) -> Outcome {
+        Ok(vec![Action(format!("hugs {}.", ctx.nick).into())])
     }
 }
 
@@ -0,0 +1,171 @@
+use super::*;
+use crate::db::{GLOBAL, Ignore, ignore, scoped};
+
+pub struct Ignores {
+    remove: bool
+}
+
+impl Command for Ignores {
+    fn cmds(&self) -> Vec<String> {
+        if self.remove { own(&["unignore"]) } else { own(&["ignore"]) }
+    }
+    fn spec(&self) -> Spec {
+        let spec = if self.remove {
+            Spec::new().param("mask", Kind::Word)
+        } else {
+            Spec::new().optional("mask", Kind::Word)
+        };
+        spec.flag("g", "global")
+    }
+    fn auth(&self) -> Auth { Op }
+    fn auth_for(&self, args: &Args) -> Auth {
+        if args.opt_present("global") && args.get("mask").is_some() { Owner } else { Anyone }
+    }
+    fn description(&self) -> String {
+        if self.remove {
+            "Stops ignoring a nick, hostmask or account in this channel. With --global, stops \
+             ignoring it everywhere on this network."
+        } else {
+            "Makes the bot ignore a nick, a hostmask like *!*@example.com, or an account like \
+             $a:name in this channel, other than keeping track of when they were last seen. With \
+             --global, ignores them everywhere on this network, which only the owner can do. \
+             Without a mask, lists who is ignored."
+        }.to_owned()
+    }
+    fn examples(&self) -> Vec<String> {
+        if self.remove {
+            own(&["unignore Troll", "unignore -g *!*@example.com"])
+        } else {
+            own(&["ignore", "ignore Troll", "ignore $a:troll", "ignore -g *!*@example.com"])
+        }
+    }
+
+    fn run(&mut self, args: &Args, ctx: &Context, db: &mut Db) -> Outcome {
+        let mask = match args.get("mask") {
+            None       => return Ok(vec![Reply(list(ctx, db))]),
+            Some(mask) => mask.to_lowercase()
+        };
+        let global = args.opt_present("global");
+        let ignore = Ignore {
+            channel: if global { GLOBAL } else { &ctx.channel }.to_owned(),
+            mask:    mask.to_owned(),
+            network: ctx.network.to_owned()
+        };
+        let place = if global { " everywhere on this network" } else { "" };
+        if self.remove {
+            if !self.save(ignore, db)? {
+                return Ok(vec![Reply(format!("I'm not ignoring {}{}.", mask, place).into())])
+            }
+            Ok(vec![Action(format!("stops ignoring {}{}.", mask, place).into())])
+        } else {
+            self.save(ignore, db)?;
+            Ok(vec![Action(format!("ignores {}{}.", mask, place).into())])
+        }
+    }
+}
+
+impl Ignores {
+    #[inline]
+    pub fn new(remove: bool) -> Self {
+        Self { remove }
+    }
+
+    /// Returns whether anything changed.
+    fn save(&self, ignore: Ignore, db: &mut Db) -> Result<bool, Error> {
+        let conn = db.conn()?;
+        if self.remove {
+            let deleted = diesel::delete(ignore::table
+                .filter(ignore::network.eq(&ignore.network))
+                .filter(ignore::channel.eq(&ignore.channel))
+                .filter(ignore::mask.eq(&ignore.mask))
+            ).execute(&conn)?;
+            Ok(db.ignores.remove_by(&ignore).is_some() || deleted > 0)
+        } else {
+            let inserted = diesel::insert_into(ignore::table)
+                .values(&ignore)
+                .on_conflict_do_nothing()
+                .execute(&conn)?;
+            Ok(db.ignores.insert(ignore).is_none() || inserted > 0)
+        }
+    }
+}
+
+fn show(mut ignores: Vec<&Ignore>) -> String {
+    ignores.sort();
+    ignores.into_iter().map(|x| x.mask.as_str()).collect::<Vec<&str>>().join(", ")
+}
+
+fn list(ctx: &Context, db: &Db) -> Text {
+    let here = show(db.ignores.in_channel(&scoped(&ctx.network, &ctx.channel)));
+    let everywhere = show(db.ignores.in_channel(&scoped(&ctx.network, GLOBAL)));
+    match (here.is_empty(), everywhere.is_empty()) {
+        (true, true)   => "I'm not ignoring anyone here.".into(),
+        (false, true)  => format!("Ignored here: {}.", here).into(),
+        (true, false)  => format!("Ignored everywhere on this network: {}.", everywhere).into(),
+        (false, false) => format!(
+            "Ignored here: {}. Ignored everywhere on this network: {}.", here, everywhere
+        ).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> Context {
+        let mut ctx = Context::mock("#site19", "Troll");
+        ctx.ident = "~troll".to_owned();
+        ctx.host = "bad.example.com".to_owned();
+        ctx.account = Some("trollish".to_owned());
+        ctx
+    }
+
+    fn ignore(mask: &str) -> Ignore {
+        Ignore { channel: "#site19".to_owned(), mask: mask.to_owned(), ..Ignore::default() }
+    }
+
+    #[test]
+    fn matches_masks() {
+        let ctx = ctx();
+        let account = ctx.account.as_ref().map(String::as_str);
+        let hostmask = "troll!~troll@bad.example.com";
+        for &mask in &["troll", "*!*@*.example.com", hostmask, "$a:trollish"] {
+            assert!(ignore(mask).matches(&ctx, account), "{}", mask);
+        }
+        for &mask in &["trol", "*!*@good.example.com", "$a:troll", "t?oll!*@*.org"] {
+            assert!(!ignore(mask).matches(&ctx, account), "{}", mask);
+        }
+    }
+
+    #[test]
+    fn skips_ignored() {
+        let mut db = Db::default();
+        assert!(!db.ignored(&ctx()));
+        db.ignores.insert(ignore("*!~troll@*"));
+        assert!(db.ignored(&ctx()));
+        assert!(!db.ignored(&Context::mock("#site19", "Someone")));
+        assert!(!db.ignored(&Context::mock("#site17", "Troll")));
+        let elsewhere = Context { network: "synirc".to_owned(), ..ctx() };
+        assert!(!db.ignored(&elsewhere));
+    }
+
+    #[test]
+    fn guards_global() {
+        let ignores = Ignores::new(false);
+        assert_eq!(ignores.auth_for(&ignores.spec().parse(&["-g", "troll"]).unwrap()), Owner);
+        assert_eq!(ignores.auth_for(&ignores.spec().parse(&["troll"]).unwrap()), Anyone);
+    }
+
+    #[test] #[ignore]
+    fn ignores_and_unignores() {
+        let mut db = Db::default();
+        let ctx = Context::default();
+        Ignores::new(false).test("Troll", &ctx, &mut db).unwrap();
+        assert_eq!(Ignores::new(false).test("", &ctx, &mut db).unwrap(), "Ignored here: troll.");
+        Ignores::new(true).test("troll", &ctx, &mut db).unwrap();
+        assert_eq!(
+            Ignores::new(true).test("troll", &ctx, &mut db).unwrap(),
+            "I'm not ignoring troll."
+        );
+    }
+}
@@ -1,10 +1,13 @@
 use select::document::Document;
 use select::predicate::{Class, Name, Predicate};
 
+use hashbrown::HashMap;
 use std::time::SystemTime;
 
 use super::*;
+use crate::db::Page;
 use crate::util;
+use crate::wikidot::Wikidot;
 
 const LIMIT: usize = 3;
 
@@ -14,12 +17,19 @@ impl Command for LastCreated {
     fn cmds(&self) -> Vec<String> {
         own(&["lastcreated", "lc", "l"])
     }
-    fn usage(&self) -> String { "".to_owned() }
-    fn fits(&self, size: usize) -> bool { size == 0 }
+    fn spec(&self) -> Spec { Spec::new() }
     fn auth(&self) -> Auth { Anyone }
+    fn cost(&self) -> u32 { NETWORK_COST }
+    fn description(&self) -> String {
+        "Shows the most recently created pages on the wiki.".to_owned()
+    }
 
-    fn run(&mut self, _: &[&str], _: &Context, db: &mut Db) -> Outcome {
-        last_created(&db)
+    fn run(&mut self, _: &Args, _: &Context, db: &mut Db) -> Outcome {
+        last_created(&db.client, &db.wiki, &db.titles)
+    }
+    fn spawn(&mut self, _: &Args, _: &Context, db: &Db) -> Option<Job<Outcome>> {
+        let (cli, wiki, titles) = (db.client.clone(), db.wiki.clone(), db.titles.clone());
+        Some(Box::new(move || last_created(&cli, &wiki, &titles)))
     }
 }
 
@@ -38,14 +48,19 @@ fn lc_titles(doc: &Document) -> Vec<String> {
     titles
 }
 
-fn last_created(db: &Db) -> Result<Vec<Response>, Error> {
+pub fn show_page(page: &Page, titles: &HashMap<String, String>, wiki: &Wikidot) -> Text {
+    Text::new()
+        .bold(db::title(titles, page))
+        .plain(format!(" ({} ago by {}): ", util::ago(page.created_at), page.created_by))
+        .link(format!("http://{}/{}", wiki.root, page.id))
+}
+
+fn last_created(cli: &reqwest::Client, wiki: &Wikidot, titles: &HashMap<String, String>)
+-> Result<Vec<Response>, Error> {
     let mut responses = Vec::new();
-    let page = Document::from_read(db.client.get(&db.wiki.lc).send()?)?;
-    db.wiki.walk(SystemTime::UNIX_EPOCH, &lc_titles(&page), &db.client, |page, _| {
-        responses.push(Reply(format!(
-            "\x02{}\x02 ({} ago by {}): http://{}/{}", 
-            db.title(&page), util::ago(page.created_at), page.created_by, db.wiki.root, page.id
-        )));
+    let page = Document::from_read(cli.get(&wiki.lc).send()?)?;
+    wiki.walk(SystemTime::UNIX_EPOCH, &lc_titles(&page), cli, |page, _| {
+        responses.push(Reply(show_page(&page, titles, wiki)));
         Ok(())
     }).map_err(Throw)?;
     Ok(responses)
@@ -57,7 +72,9 @@ mod tests {
 
     #[test]
     fn parses_lc() {
-        let lc = last_created(&Db::default()).expect("Error loading Last Created");
+        let db = Db::default();
+        let lc = last_created(&db.client, &db.wiki, &db.titles)
+            .expect("Error loading Last Created");
         assert_eq!(lc.len(), LIMIT);
     }
 }
@@ -9,65 +9,71 @@ impl Command for Memo {
     fn cmds(&self) -> Vec<String> {
         if self.shortcut { own(&["rem"]) } else { own(&["memo"]) }
     }
-    fn usage(&self) -> String { 
+    fn spec(&self) -> Spec {
+        let entry = Spec::new().param("user", Kind::Word).rest("message");
         if self.shortcut {
-            "<user> <message>".to_owned()
+            entry
         } else {
-            "<user> | memo (add|append|del) <user> <message>".to_owned() 
+            Spec::new()
+                .optional("user", Kind::Word)
+                .sub("add", entry.clone())
+                .sub("append", entry.clone())
+                .sub("del", entry)
         }
-    }   
-    fn fits(&self, i: usize) -> bool { 
-        if self.shortcut { i >= 2 } else { i != 2 }
     }
     fn auth(&self) -> Auth { Anyone }
-
-    fn run(&mut self, args: &[&str], ctx: &Context, db: &mut Db) -> Outcome {
+    fn description(&self) -> String {
         if self.shortcut {
-            let (nick, msg) = args.split_first().unwrap();
-            let message = self.append(&msg.join(" "), &nick.to_lowercase(), ctx, db)?;
-            Ok(vec![
-                Action(attribute(nick, ctx)),
-                Reply(format!("New memo: \x1d{}\x1d", message))
-            ])
+            "Adds to the end of a user's memo in this channel."
         } else {
-            let destructured: (&[&str], &[&str]) = 
-                if args.len() < 2 { (args, &[]) } else { args.split_at(2) };
-            match destructured {
-                ([], _)     => 
-                    Ok(vec![Reply(format!("\x1d{}\x1d", self.get(&ctx.user, ctx, db)?))]),
-                ([nick], _) => 
-                    Ok(vec![Reply(format!("\x1d{}\x1d", self.get(&nick.to_lowercase(), ctx, db)?))]),
-                (["add", nick], msg) => match self.get(&nick.to_lowercase(), ctx, db) {
-                    Ok(s) => Ok(vec![Reply(format!(
-                        "{} already has a memo. To delete it, use .memo del {} \x1d{}\x1d",
-                        nick, nick, s
-                    ))]),
-                    Err(NoResults) => {
-                        self.insert(&msg.join(" "), &nick.to_lowercase(), ctx, db)?;
-                        Ok(vec![Action(attribute(nick, ctx))])
-                    },
-                    Err(e) => Err(e)
-                },
-                (["append", nick], msg) => {
-                    let message = self.append(&msg.join(" "), &nick.to_lowercase(), ctx, db)?;
-                    Ok(vec![
-                        Action(attribute(nick, ctx)),
-                        Reply(format!("New memo: \x1d{}\x1d", message))
-                    ])
-                },
-                (["del", nick], msg) => match self.get(&nick.to_lowercase(), ctx, db) {
-                    Err(NoResults) => Ok(vec![Reply(format!("{} doesn't have a memo.", nick))]),
-                    Err(e)         => Err(e),
-                    Ok(ref s) if msg != s.split(' ').collect::<Vec<&str>>().as_slice() => 
-                        Ok(vec![Reply(
-                            format!("To delete that memo, use .memo del {} \x1d{}\x1d", nick, s)
-                        )]),
-                    _ => {
-                            self.remove(&nick.to_lowercase(), ctx, db)?;
-                            Ok(vec![Action(format!("erases {}'s memo.", nick))])
-                        }
+            "Shows, adds to or deletes a user's memo in this channel."
+        }.to_owned()
+    }
+    fn examples(&self) -> Vec<String> {
+        if self.shortcut {
+            own(&["rem Jabyrwock Also likes coffee."])
+        } else {
+            own(&["memo Jabyrwock", "memo add Jabyrwock Likes tea."])
+        }
+    }
+
+    fn run(&mut self, args: &Args, ctx: &Context, db: &mut Db) -> Outcome {
+        let nick = args.get("user").unwrap_or(ctx.user.as_str());
+        let user = nick.to_lowercase();
+        let msg = args.text("message");
+        let sub = if self.shortcut { Some("append") } else { args.sub };
+        match sub {
+            None => Ok(vec![Reply(Text::new().italic(self.get(&user, ctx, db)?))]),
+            Some("add") => match self.get(&user, ctx, db) {
+                Ok(s) => Ok(vec![Reply(Text::new()
+                    .plain(format!("{} already has a memo. ", nick))
+                    .plain(format!("To delete it, use .memo del {} ", nick))
+                    .italic(s)
+                )]),
+                Err(NoResults) => {
+                    self.insert(msg, &user, ctx, db)?;
+                    Ok(vec![Action(attribute(nick, ctx))])
                 },
-                _ => Err(InvalidArgs)
+                Err(e) => Err(e)
+            },
+            Some("append") => {
+                let message = self.append(msg, &user, ctx, db)?;
+                Ok(vec![
+                    Action(attribute(nick, ctx)),
+                    Reply(Text::new().plain("New memo: ").italic(message))
+                ])
+            },
+            _ => match self.get(&user, ctx, db) {
+                Err(NoResults) => Ok(vec![Reply(format!("{} doesn't have a memo.", nick).into())]),
+                Err(e)         => Err(e),
+                Ok(ref s) if msg != s.as_str() => Ok(vec![Reply(Text::new()
+                    .plain(format!("To delete that memo, use .memo del {} ", nick))
+                    .italic(s)
+                )]),
+                _ => {
+                    self.remove(&user, ctx, db)?;
+                    Ok(vec![Action(format!("erases {}'s memo.", nick).into())])
+                }
             }
         }
     }
@@ -92,6 +98,7 @@ impl Memo {
     pub fn get(&self, user: &str, ctx: &Context, db: &Db) -> Result<String, Error> { 
         Ok(
             memo::table
+                .filter(memo::network.eq(&ctx.network))
                 .filter(memo::channel.eq(&ctx.channel))
                 .filter(memo::user.eq(user))
             .first::<db::Memo>(&db.conn()?)?
@@ -102,6 +109,7 @@ impl Memo {
     pub fn remove(&mut self, user: &str, ctx: &Context, db: &Db) -> Result<String, Error> {
         Ok(
             diesel::delete(memo::table
+                .filter(memo::network.eq(&ctx.network))
                 .filter(memo::channel.eq(&ctx.channel))
                 .filter(memo::user.eq(user)))
             .returning(memo::message)
@@ -113,11 +121,12 @@ impl Memo {
         let memo = db::Memo { 
             channel: ctx.channel.to_owned(),
             user:    user.to_owned(),
-            message: message.to_owned()
+            message: message.to_owned(),
+            network: ctx.network.to_owned()
         };
         diesel::insert_into(memo::table)
             .values(&memo)
-            .on_conflict((memo::channel, memo::user))
+            .on_conflict((memo::network, memo::channel, memo::user))
             .do_update()
             .set(upsert(memo::message))
             .execute(&db.conn()?)?;
@@ -125,10 +134,10 @@ impl Memo {
     }
 }
 
-fn attribute(nick: &str, ctx: &Context) -> String {
+fn attribute(nick: &str, ctx: &Context) -> Text {
     if nick.to_lowercase() == ctx.user {
         format!("writes down {}'s memo.", nick)
     } else {
         format!("writes down {}'s memo from {}.", nick, ctx.nick)
-    }
+    }.into()
 }
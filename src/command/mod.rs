@@ -6,17 +6,28 @@ use hashbrown::HashMap;
 mod choose;
 mod define;
 mod disable;
+mod disabled;
 mod forget;
 mod google;
+mod help;
 mod hug;
+mod ignore;
 mod lastcreated;
 mod memo;
+mod more;
 mod name;
+mod page;
+mod permission;
+mod prefix;
 mod quit;
+mod random;
+mod ratelimit;
+mod rating;
 mod reload;
 mod remindme;
 mod roll;
 mod seen;
+mod suggest;
 mod tell;
 mod wikipedia;
 mod zyn;
@@ -24,27 +35,50 @@ mod zyn;
 mod author;
 mod search;
 
-use crate::{Context, db, env};
+use crate::{Context, db, env, links};
+use crate::args::{Args, Kind, Spec};
 use crate::auth::*;
 use crate::db::{Db, Pool};
 use crate::error::*;
 use crate::logging::*;
 use crate::output::{Output, Response};
 use crate::output::Response::*;
+use crate::text::Text;
 use crate::util::own;
+use crate::worker::Job;
+
+use self::Run::*;
+
+/// Commands that call out to other sites cost more, since they're slow and some have quotas.
+const NETWORK_COST: u32 = 3;
 
 trait Command {
     fn cmds(&self) -> Vec<String>;
-    fn usage(&self) -> String;
+    /// The command's arguments, which are parsed before it runs and also give its usage.
+    fn spec(&self) -> Spec;
     fn auth(&self) -> Auth;
-    fn fits(&self, size: usize) -> bool;
-    fn run(&mut self, args: &[&str], ctx: &Context, db: &mut Db) -> Outcome;
+    /// The level some arguments need on top of the command's own, like changing something for a
+    /// whole network.
+    fn auth_for(&self, _args: &Args) -> Auth { Anyone }
+    fn description(&self) -> String;
+    /// Example invocations, without a prefix.
+    fn examples(&self) -> Vec<String> { Vec::new() }
+    fn run(&mut self, args: &Args, ctx: &Context, db: &mut Db) -> Outcome;
+
+    /// How much of a user's rate limit budget the command uses.
+    fn cost(&self) -> u32 { 1 }
+
+    /// Commands that block on the network return their work as a job instead, so it can run off
+    /// the IRC thread. The job can't touch `db`, so anything it needs has to be copied out first.
+    fn spawn(&mut self, _args: &Args, _ctx: &Context, _db: &Db) -> Option<Job<Outcome>> {
+        None
+    }
     
     #[cfg(test)]
     fn test(&mut self, query: &str, ctx: &Context, db: &mut Db) -> Result<String, Error> {
-        let args: Vec<&str> = query.split(' ').filter(|x| !x.is_empty()).collect();
+        let args = self.spec().parse_message(query)?;
         let res = self.run(&args, ctx, db)?;
-        let lines: Vec<String> = res.into_iter().map(|x| x.text().to_owned()).collect();
+        let lines: Vec<String> = res.into_iter().map(|x| x.text()).collect();
         Ok(lines.join("\n"))
     }
     #[cfg(test)]
@@ -53,6 +87,12 @@ trait Command {
     }
 }
 
+/// A command's outcome, or a job that will produce it off the IRC thread.
+pub enum Run {
+    Ready(Outcome),
+    Spawned(Job<Outcome>)
+}
+
 #[derive(Default)]
 pub struct Commands {
     stash:  Stash<Box<dyn Command + 'static>, usize>,
@@ -63,17 +103,26 @@ pub struct Commands {
 impl Commands {
     pub fn new(pool: &Pool) -> Self {
         let mut x = Self::default();
-        x.store(author::Author::new());
+        x.store(author::Author);
         x.store(choose::Choose::new());
         x.store(define::Define::new());
+        x.store(disabled::Disabled);
         x.store(forget::Forget);
         x.store(hug::Hug);
+        x.store(ignore::Ignores::new(false));
+        x.store(ignore::Ignores::new(true));
         x.store(lastcreated::LastCreated);
+        x.store(more::More);
+        x.store(page::PageInfo);
+        x.store(prefix::Prefixes);
         x.store(quit::Quit);
+        x.store(random::Random::new());
+        x.store(ratelimit::RateLimits);
+        x.store(rating::Rating);
         x.store(reload::Reload);
-        x.store(remindme::Remindme::new());
+        x.store(remindme::Remindme);
         x.store(roll::Roll::new());
-        x.store(search::Search::new());
+        x.store(search::Search);
         x.store(seen::Seen);
         x.store(tell::Tell);
         x.store(wikipedia::Wikipedia::new());
@@ -89,53 +138,103 @@ impl Commands {
             Err(e)    => log(ERROR, &format!("Error creating name command: {}", e)),
             Ok(names) => x.store(names)
         }
+        x.canons.insert(links::CANON.to_owned(), links::CANON.to_owned());
+        x.canons.insert(suggest::CANON.to_owned(), suggest::CANON.to_owned());
+        x.canons.insert("help".to_owned(), "help".to_owned());
+        x.canons.insert("h".to_owned(), "help".to_owned());
         for &i in &[false, true] {
             x.store(disable::Disable::new(i, x.canons.clone()));
         }
-        x.usages.insert("help".to_owned(), "<command>".to_owned());
-        x.usages.insert("h".to_owned(), "<command>".to_owned());
-        x.usages.insert("showmore".to_owned(), "<number>".to_owned());
-        x.usages.insert("sm".to_owned(), "<number>".to_owned());
+        let defaults = x.canons
+            .iter()
+            .filter_map(|(cmd, canon)| {
+                let default = x.get(cmd)?.auth();
+                Some((cmd.to_owned(), (canon.to_owned(), default)))
+            })
+            .collect();
+        x.store(permission::Permissions::new(defaults));
+        x.usages.insert("help".to_owned(), "[<command>]".to_owned());
+        x.usages.insert("h".to_owned(), "[<command>]".to_owned());
+        x.usages.insert("showmore".to_owned(), "<number>[-<number>]".to_owned());
+        x.usages.insert("sm".to_owned(), "<number>[-<number>]".to_owned());
         x
     }
 
     fn store<T: Command + 'static>(&mut self, t: T) {
         let cmds = t.cmds();
         let canon = cmds[0].to_owned();
-        let usage = t.usage();
+        let spec = t.spec();
         let key = self.stash.put(Box::new(t));
         for cmd in cmds {
-            self.usages.insert(cmd.to_owned(), usage.to_owned());
+            self.usages.insert(cmd.to_owned(), spec.usage(&cmd));
             self.canons.insert(cmd.to_owned(), canon.to_owned());
             self.keys.insert(cmd, key);
         }
     }
 
-    pub fn usage(&self, cmd: &str) -> String {
+    pub fn knows(&self, cmd: &str) -> bool {
+        self.canons.contains_key(cmd)
+    }
+
+    pub fn usage(&self, cmd: &str) -> Text {
         match self.usages.get(cmd) {
-            None    => "I don't know that command.".to_owned(),
-            Some(x) => format!("Usage: \x02{}\x02 {}", cmd, x)
+            None    => "I don't know that command.".into(),
+            Some(x) => Text::new().plain("Usage: ").bold(cmd).plain(" ").plain(x)
+        }
+    }
+
+    /// Commands that only touch `db` run immediately, so whatever they leave behind can be stored
+    /// right away. Slow ones come back as a job to run off the IRC thread. `typed` is the text of
+    /// `args` as it was typed.
+    pub fn run<T: Output>(
+        &mut self, cmd: &str, args: &[&str], typed: &str, ctx: &Context, db: &mut Db, irc: &T
+    ) -> Run {
+        match self.dispatch(cmd, args, typed, ctx, db, irc) {
+            Ok(run) => run,
+            Err(e)  => Ready(Err(e))
         }
     }
 
-    pub fn run<T: Output>(&mut self, cmd: &str, args: &[&str], ctx: &Context, db: &mut Db, irc: &T) 
-    -> Outcome {
-        if db.silences.contains(&ctx.channel, self.canons.get(cmd).ok_or(Unknown)?) {
+    fn dispatch<T: Output>(
+        &mut self, cmd: &str, args: &[&str], typed: &str, ctx: &Context, db: &mut Db, irc: &T
+    ) -> Result<Run, Error> {
+        let auth = db.auth(ctx, irc);
+        let canon = match self.canons.get(cmd) {
+            Some(canon) => canon,
+            None        => {
+                let xs = self.suggest(cmd, args, ctx, db, auth);
+                return Err(if xs.is_empty() { Unknown } else { Ambiguous(0, xs) })
+            }
+        };
+        if db.silenced(ctx, canon) {
             Err(Unauthorized)
         } else {
-            match (cmd, args) {
-                ("help", [query]) => { Ok(vec![Reply(self.usage(query))]) }
+            match (canon.as_str(), args) {
+                ("help", [])      => {
+                    db.spend(ctx, 1, auth)?;
+                    Ok(Ready(Ok(self.list(ctx, db, auth))))
+                },
+                ("help", [query]) => {
+                    db.spend(ctx, 1, auth)?;
+                    Ok(Ready(Ok(self.describe(query, ctx, db))))
+                },
                 ("help", _)       => Err(InvalidArgs),
                 _ => {
                     let &key = self.keys.get(cmd).ok_or(Unknown)?;
                     let x = self.stash.get_mut(key).ok_or(Unknown)?;
                     
-                    if x.auth() > db.auth(ctx, irc) {
+                    if db.permission(ctx, canon, x.auth()) > auth {
                         Err(Unauthorized)
-                    } else if !x.fits(args.len()) {
-                        Err(InvalidArgs)
                     } else {
-                        x.run(args, ctx, db)
+                        let args = x.spec().parse_message(typed)?;
+                        if x.auth_for(&args) > auth {
+                            return Err(Unauthorized)
+                        }
+                        db.spend(ctx, x.cost(), auth)?;
+                        match x.spawn(&args, ctx, db) {
+                            Some(job) => Ok(Spawned(job)),
+                            None      => Ok(Ready(x.run(&args, ctx, db)))
+                        }
                     }
                 }
             }
@@ -143,6 +242,12 @@ impl Commands {
     }
 }
 
+/// For `spawn` implementations that fail before they have any slow work to hand off.
+#[inline]
+fn done(outcome: Outcome) -> Job<Outcome> {
+    Box::new(move || outcome)
+}
+
 #[inline]
 fn abbrev(s: &str) -> Vec<String> {
     (0..s.len()).rev().map(|i| s[..=i].to_owned()).collect()
@@ -0,0 +1,59 @@
+use super::*;
+
+pub struct More;
+
+impl Command for More {
+    fn cmds(&self) -> Vec<String> {
+        own(&["more"])
+    }
+    fn spec(&self) -> Spec { Spec::new() }
+    fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String {
+        "Continues the bot's last long reply or \"Did you mean\" message to you.".to_owned()
+    }
+
+    fn run(&mut self, _: &Args, ctx: &Context, db: &mut Db) -> Outcome {
+        let held = db.held.next(ctx);
+        if !held.is_empty() {
+            return Ok(held)
+        }
+        match db.choices.get_mut(ctx).and_then(|x| x.next()) {
+            None    => Ok(vec![Reply("There's nothing more to show.".into())]),
+            Some(s) => Ok(vec![Reply(s)])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::choices::Choices;
+
+    #[test]
+    fn continues_choices() {
+        let mut db = Db::default();
+        let ctx = Context::mock("#site19", "Alice");
+        let mut choices = Choices::new("s", own(&["a", "b"]));
+        choices.next();
+        db.choices.insert(&ctx, choices);
+        assert_eq!(More.test("", &ctx, &mut db).unwrap(), "Or: \x022.\x02 b");
+        let bob = Context::mock("#site19", "Bob");
+        assert_eq!(More.test("", &bob, &mut db).unwrap(), "There's nothing more to show.");
+    }
+
+    #[test]
+    fn continues_long_replies() {
+        let mut db = Db::default();
+        let ctx = Context::mock("#site19", "Alice");
+        db.held.insert(&ctx, vec![Reply("rest".into())]);
+        db.choices.insert(&ctx, Choices::new("s", own(&["a", "b"])));
+        assert_eq!(More.test("", &ctx, &mut db).unwrap(), "rest");
+        let choices = More.test("", &ctx, &mut db).unwrap();
+        assert_eq!(choices, "Did you mean: \x021.\x02 a, \x022.\x02 b");
+    }
+
+    #[test]
+    fn nothing_more() {
+        assert_eq!(More.test_def("").unwrap(), "There's nothing more to show.");
+    }
+}
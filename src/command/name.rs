@@ -34,18 +34,19 @@ impl Command for Name {
     fn cmds(&self) -> Vec<String> {
         own(&["name", "names"])
     }
-    fn usage(&self) -> String { "[-f|-m]".to_owned() }
-    fn fits(&self, size: usize) -> bool { size <= 1 }
+    fn spec(&self) -> Spec { Spec::new().flag("f", "female").flag("m", "male") }
     fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String { "Generates a random name.".to_owned() }
+    fn examples(&self) -> Vec<String> { own(&["name", "name -f"]) }
 
-    fn run(&mut self, args: &[&str], _: &Context, _: &mut Db) -> Outcome {
-        let gender = match args {
-            []     => Ok(Gender::Any),
-            ["-f"] => Ok(Gender::Female),
-            ["-m"] => Ok(Gender::Male),
-            _      => Err(InvalidArgs)
+    fn run(&mut self, args: &Args, _: &Context, _: &mut Db) -> Outcome {
+        let gender = match (args.opt_present("f"), args.opt_present("m")) {
+            (false, false) => Ok(Gender::Any),
+            (true, false)  => Ok(Gender::Female),
+            (false, true)  => Ok(Gender::Male),
+            (true, true)   => Err(BadArgs("Pick either -f or -m, not both.".into()))
         }?;
-        Ok(vec![Reply(self.gen(gender))])
+        Ok(vec![Reply(self.gen(gender).into())])
     }
 }
 
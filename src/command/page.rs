@@ -0,0 +1,154 @@
+use multimap::MultiMap;
+
+use super::*;
+use crate::db::{Attribution, Page, attribution, page, pages, tag};
+use crate::util;
+
+pub struct PageInfo;
+
+impl Command for PageInfo {
+    fn cmds(&self) -> Vec<String> {
+        own(&["page", "pageinfo", "pi"])
+    }
+    fn spec(&self) -> Spec { Spec::new().rest("page name or title") }
+    fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String {
+        "Shows a page's rating, creation date, authors and tags.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> { own(&["page scp-173", "page The Sculpture"]) }
+
+    fn run(&mut self, args: &Args, _: &Context, db: &mut Db) -> Outcome {
+        let page = find(args.text("page name or title"), db)?;
+        let conn = db.conn()?;
+        let mut tags: Vec<String> = tag::table
+            .filter(tag::page_id.eq(&page.id))
+            .select(tag::name)
+            .load(&conn)?;
+        tags.sort();
+        let attributions: Vec<Attribution> = attribution::table
+            .filter(attribution::page_id.eq(&page.id))
+            .load(&conn)?;
+        Ok(vec![Reply(describe(&db.title(&page), &page, &tags, &attributions, &db.wiki.root))])
+    }
+}
+
+/// Looks up a page by its exact name, then its exact title or subtitle, then part of its title.
+pub fn find(query: &str, db: &Db) -> Result<Page, Error> {
+    let conn = db.conn()?;
+    let lower = query.to_lowercase();
+    let mut ids: Vec<String> = db.titles
+        .iter()
+        .filter(|(_, subtitle)| subtitle.to_lowercase() == lower)
+        .map(|(id, _)| id.to_owned())
+        .collect();
+    let mut pages: Vec<Page> = page::table
+        .filter(page::id.eq(&lower)
+            .or(page::title.ilike(pages::matching(query)))
+            .or(page::id.eq_any(ids.split_off(0)))
+        )
+        .load(&conn)?;
+    if let Some(i) = pages.iter().position(|x| x.id == lower) {
+        return Ok(pages.swap_remove(i))
+    }
+    if pages.is_empty() {
+        pages = page::table
+            .filter(page::title.ilike(pages::containing(query)))
+            .order(page::created_at.desc())
+            .load(&conn)?;
+    }
+    match pages.len() {
+        0 => Err(NoResults),
+        1 => Ok(pages.remove(0)),
+        size => Err(Ambiguous(size as i64, pages.iter().map(|x| x.title.to_owned()).collect()))
+    }
+}
+
+fn describe(title: &str, page: &Page, tags: &[String], attributions: &[Attribution], root: &str)
+-> Text {
+    let mut s = Text::new()
+        .bold(title)
+        .plain(" (")
+        .bold(util::rating(i64::from(page.rating)))
+        .plain(format!(
+            ") was created on {} ({} ago) by {}.",
+            page.created_at.format("%B %-d, %Y"),
+            util::ago(page.created_at),
+            page.created_by
+        ));
+    let kinds: MultiMap<&str, &str> = attributions
+        .iter()
+        .filter(|x| x.user != page.created_by || x.kind != "author")
+        .map(|x| (x.kind.as_str(), x.user.as_str()))
+        .collect();
+    for (kind, users) in kinds.iter_all() {
+        s.push_str(" ");
+        s.push_str(&attribution_label(kind));
+        s.push_str(" ");
+        s.push_str(&users.join(", "));
+        s.push_str(".");
+    }
+    if !tags.is_empty() {
+        s.push_str(" Tags: ");
+        s.push_str(&tags.join(", "));
+        s.push_str(".");
+    }
+    s.plain(" ").link(format!("http://{}/{}", root, page.id))
+}
+
+fn attribution_label(kind: &str) -> String {
+    match kind {
+        "author"     => "Co-written by".to_owned(),
+        "rewrite"    => "Rewritten by".to_owned(),
+        "translator" => "Translated by".to_owned(),
+        _            => {
+            let mut label = kind.to_owned();
+            if let Some(first) = label.get_mut(..1) {
+                first.make_ascii_uppercase();
+            }
+            label.push_str(":");
+            label
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::Format;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn describes_pages() {
+        let page = Page {
+            id:         "scp-173".to_owned(),
+            created_at: Utc.ymd(2008, 7, 19).and_hms(0, 0, 0),
+            created_by: "moto42".to_owned(),
+            rating:     5,
+            title:      "SCP-173".to_owned(),
+            ..Page::default()
+        };
+        let attribute = |user: &str, kind: &str| Attribution {
+            page_id: page.id.to_owned(),
+            user:    user.to_owned(),
+            kind:    kind.to_owned()
+        };
+        let attributions = vec![
+            attribute("moto42", "author"),
+            attribute("a", "translator"),
+            attribute("b", "translator")
+        ];
+        let tags = own(&["euclid", "scp"]);
+        let s = describe("SCP-173: The Sculpture", &page, &tags, &attributions, "w")
+            .render(Format::Irc);
+        assert!(s.starts_with(
+            "\x02SCP-173: The Sculpture\x02 (\x02+5\x02) was created on July 19, 2008 ("
+        ));
+        assert!(s.ends_with("by moto42. Translated by a, b. Tags: euclid, scp. http://w/scp-173"));
+    }
+
+    #[test]
+    fn labels_attributions() {
+        assert_eq!(attribution_label("translator"), "Translated by");
+        assert_eq!(attribution_label("editor"), "Editor:");
+    }
+}
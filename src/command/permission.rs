@@ -0,0 +1,172 @@
+use std::cmp;
+
+use super::*;
+use crate::db::{Permission, permission, scoped, upsert};
+
+const LEVELS: &[&str] = &["anyone", "voice", "half-op", "op", "owner"];
+
+pub struct Permissions {
+    /// Each command's canonical name and default level, by every name it goes by.
+    defaults: HashMap<String, (String, Auth)>
+}
+
+impl Command for Permissions {
+    fn cmds(&self) -> Vec<String> {
+        own(&["permission", "perm"])
+    }
+    fn spec(&self) -> Spec {
+        Spec::new()
+            .optional("command", Kind::Word)
+            .sub("set", Spec::new()
+                .param("command", Kind::Word)
+                .param("level", Kind::Choice(LEVELS))
+            )
+            .sub("reset", Spec::new().param("command", Kind::Word))
+    }
+    fn auth(&self) -> Auth { Op }
+    fn description(&self) -> String {
+        "Shows or changes who can use commands in this channel.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> {
+        own(&["perm google", "perm set google voice", "perm reset google"])
+    }
+
+    fn run(&mut self, args: &Args, ctx: &Context, db: &mut Db) -> Outcome {
+        let query = match args.get("command") {
+            None        => return Ok(vec![Reply(list(ctx, db))]),
+            Some(query) => query.trim_start_matches(|c| c == '.' || c == '!').to_lowercase()
+        };
+        let (canon, default) = match self.defaults.get(&query) {
+            Some((canon, default)) => (canon.to_owned(), *default),
+            None                   => return Ok(vec![Reply(
+                "I'm sorry, I don't know that command.".into()
+            )])
+        };
+        let current = db.permission(ctx, &canon, default);
+        let level = match args.sub {
+            None        => return Ok(vec![Reply(show(&canon, current, default))]),
+            Some("set") => args.text("level").parse().map_err(|_| InvalidArgs)?,
+            _           => default
+        };
+        // Channel modes stop at op, so going past that takes an owner's account.
+        if cmp::max(default, cmp::max(current, level)) > Op && db.granted(ctx) < Owner {
+            return Ok(vec![Reply(format!("Only owners can change who can use .{}.", canon).into())])
+        }
+        self.save(&canon, level, default, ctx, db)?;
+        Ok(vec![Reply(show(&canon, level, default))])
+    }
+}
+
+impl Permissions {
+    #[inline]
+    pub fn new(defaults: HashMap<String, (String, Auth)>) -> Self {
+        Self { defaults }
+    }
+
+    /// Going back to the default deletes the override rather than storing a copy of it.
+    fn save(&self, canon: &str, level: Auth, default: Auth, ctx: &Context, db: &mut Db)
+    -> Result<(), Error> {
+        let conn = db.conn()?;
+        if level == default {
+            db.permissions.remove(&scoped(&ctx.network, &ctx.channel), canon);
+            diesel::delete(permission::table
+                .filter(permission::network.eq(&ctx.network))
+                .filter(permission::channel.eq(&ctx.channel))
+                .filter(permission::command.eq(canon))
+            ).execute(&conn)?;
+        } else {
+            let permission = Permission {
+                channel: ctx.channel.to_owned(),
+                command: canon.to_owned(),
+                auth:    level.to_string(),
+                network: ctx.network.to_owned()
+            };
+            diesel::insert_into(permission::table)
+                .values(&permission)
+                .on_conflict((permission::network, permission::channel, permission::command))
+                .do_update()
+                .set(upsert(permission::auth))
+                .execute(&conn)?;
+            db.permissions.insert(permission);
+        }
+        Ok(())
+    }
+}
+
+fn list(ctx: &Context, db: &Db) -> Text {
+    let mut overrides = db.permissions.in_channel(&scoped(&ctx.network, &ctx.channel));
+    if overrides.is_empty() {
+        return "Every command here has its usual permissions.".into()
+    }
+    overrides.sort_by(|x, y| x.command.cmp(&y.command));
+    let overrides = overrides
+        .into_iter()
+        .map(|x| Text::new().bold(format!(".{}", x.command)).plain(format!(": {}", x.auth)));
+    Text::new().plain("Changed here: ").append(Text::list(overrides)).plain(".")
+}
+
+fn show(canon: &str, level: Auth, default: Auth) -> Text {
+    let cmd = format!(".{}", canon);
+    let text = match level {
+        Anyone => Text::new().plain("Anyone can use ").bold(cmd).plain(" here"),
+        _      => Text::new().bold(cmd).plain(format!(" needs {} here", level))
+    };
+    if level == default {
+        text.plain(".")
+    } else {
+        text.plain(format!(" (usually {}).", default))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new() -> Permissions {
+        let mut defaults = HashMap::new();
+        defaults.insert("g".to_owned(), ("google".to_owned(), Anyone));
+        defaults.insert("forget".to_owned(), ("forget".to_owned(), Owner));
+        Permissions::new(defaults)
+    }
+
+    #[test]
+    fn shows_levels() {
+        assert_eq!(new().test_def("!g").unwrap(), "Anyone can use \x02.google\x02 here.");
+        assert_eq!(
+            show("google", Voice, Anyone).render(crate::text::Format::Irc),
+            "\x02.google\x02 needs voice here (usually anyone)."
+        );
+    }
+
+    #[test]
+    fn guards_owner_commands() {
+        assert_eq!(
+            new().test_def("set forget op").unwrap(),
+            "Only owners can change who can use .forget."
+        );
+        assert!(new().test_def("set g admin").is_err());
+    }
+
+    #[test]
+    fn keeps_networks_apart() {
+        let mut db = Db::default();
+        let ctx = Context::default();
+        db.permissions.insert(Permission {
+            channel: ctx.channel.to_owned(),
+            command: "google".to_owned(),
+            auth:    "voice".to_owned(),
+            network: "synirc".to_owned()
+        });
+        assert_eq!(db.permission(&ctx, "google", Anyone), Anyone);
+    }
+
+    #[test] #[ignore]
+    fn overrides_levels() {
+        let mut db = Db::default();
+        let ctx = Context::default();
+        new().test("set g voice", &ctx, &mut db).unwrap();
+        assert_eq!(db.permission(&ctx, "google", Anyone), Voice);
+        new().test("reset g", &ctx, &mut db).unwrap();
+        assert_eq!(db.permission(&ctx, "google", Anyone), Anyone);
+    }
+}
@@ -0,0 +1,111 @@
+use super::*;
+use crate::db::{Prefix, prefix, scoped, upsert};
+
+/// Anything longer is almost certainly a mistake, and nobody would type it anyway.
+const MAX_LENGTH: usize = 10;
+const MAX_PREFIXES: usize = 5;
+
+pub struct Prefixes;
+
+impl Command for Prefixes {
+    fn cmds(&self) -> Vec<String> {
+        own(&["prefix"])
+    }
+    fn spec(&self) -> Spec {
+        Spec::new()
+            .sub("set", Spec::new().rest("prefixes"))
+            .sub("brackets", Spec::new().param("setting", Kind::Choice(&["on", "off"])))
+            .sub("reset", Spec::new())
+    }
+    fn auth(&self) -> Auth { Op }
+    fn description(&self) -> String {
+        "Shows or changes how commands are invoked in this channel.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> { own(&["prefix set ? !", "prefix brackets off"]) }
+
+    fn run(&mut self, args: &Args, ctx: &Context, db: &mut Db) -> Outcome {
+        let mut config = db.prefix(ctx);
+        match args.sub {
+            None             => return Ok(vec![Reply(show(&config).into())]),
+            Some("set")      => config.prefixes = parse(args.text("prefixes"))?,
+            Some("brackets") => config.brackets = args.text("setting").eq_ignore_ascii_case("on"),
+            _                => config = Prefix::new(&ctx.network, &ctx.channel)
+        }
+        self.save(&config, db)?;
+        let reply = show(&config);
+        db.prefixes.insert(scoped(&config.network, &config.channel), config);
+        Ok(vec![Reply(reply.into())])
+    }
+}
+
+impl Prefixes {
+    fn save(&self, config: &Prefix, db: &Db) -> Result<(), Error> {
+        diesel::insert_into(prefix::table)
+            .values(config)
+            .on_conflict((prefix::network, prefix::channel))
+            .do_update()
+            .set((upsert(prefix::prefixes), upsert(prefix::brackets)))
+            .execute(&db.conn()?)?;
+        Ok(())
+    }
+}
+
+/// Normalizes a space-separated list of prefixes, rejecting an empty or unreasonable one.
+fn parse(s: &str) -> Result<String, Error> {
+    let prefixes: Vec<&str> = s.split_whitespace().collect();
+    if prefixes.is_empty() {
+        Err(BadArgs("I need at least one prefix.".into()))
+    } else if prefixes.len() > MAX_PREFIXES {
+        Err(BadArgs(format!("I can only use up to {} prefixes.", MAX_PREFIXES).into()))
+    } else if prefixes.iter().any(|x| x.chars().count() > MAX_LENGTH) {
+        Err(BadArgs(format!("Prefixes can be at most {} characters long.", MAX_LENGTH).into()))
+    } else {
+        Ok(prefixes.join(" "))
+    }
+}
+
+fn show(config: &Prefix) -> String {
+    format!(
+        "Commands here start with {} or my name. [Bracketed commands] are {}.",
+        config.prefixes().join(" or "),
+        if config.brackets { "on" } else { "off" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_defaults() {
+        assert_eq!(
+            Prefixes.test_def("").unwrap(), 
+            "Commands here start with ! or . or my name. [Bracketed commands] are on."
+        );
+    }
+
+    #[test]
+    fn rejects_bad_toggle() {
+        assert!(Prefixes.test_def("brackets maybe").is_err());
+        assert!(Prefixes.test_def("set").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_prefixes() {
+        assert_eq!(parse(" ?  tex: ").ok(), Some("? tex:".to_owned()));
+        assert!(parse("").is_err());
+        assert!(parse("   ").is_err());
+        assert!(parse("a b c d e f").is_err());
+        assert!(parse(&"!".repeat(MAX_LENGTH + 1)).is_err());
+        assert!(Prefixes.test_def("set \"\"").is_err());
+    }
+
+    #[test] #[ignore]
+    fn sets_prefixes() {
+        let mut db = Db::default();
+        let ctx = Context::default();
+        Prefixes.test("set ? tex:", &ctx, &mut db).unwrap();
+        assert_eq!(db.prefix(&ctx).prefixes(), vec!["?", "tex:"]);
+        Prefixes.test("reset", &ctx, &mut db).unwrap();
+    }
+}
@@ -6,12 +6,12 @@ impl Command for Quit {
     fn cmds(&self) -> Vec<String> {
         own(&["quit"])
     }
-    fn usage(&self) -> String { "".to_owned() }
-    fn fits(&self, size: usize) -> bool { size == 0 }
+    fn spec(&self) -> Spec { Spec::new() }
     fn auth(&self) -> Auth { Owner }
+    fn description(&self) -> String { "Shuts down the bot.".to_owned() }
 
-    fn run(&mut self, _: &[&str], _: &Context, _: &mut Db) -> Outcome {
-        Ok(vec![Response::Quit("Shutting down, bleep bloop.".to_owned())])
+    fn run(&mut self, _: &Args, _: &Context, _: &mut Db) -> Outcome {
+        Ok(vec![Response::Quit("Shutting down, bleep bloop.".into())])
     }
 }
 
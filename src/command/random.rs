@@ -0,0 +1,68 @@
+use rand::Rng;
+use rand::rngs::ThreadRng;
+
+use super::*;
+use crate::db::{Page, page, pages};
+
+#[derive(Default)]
+pub struct Random {
+    rng: ThreadRng
+}
+
+impl Command for Random {
+    fn cmds(&self) -> Vec<String> {
+        own(&["random", "randompage", "rp"])
+    }
+    fn spec(&self) -> Spec { pages::options(Spec::new().optional_rest("title")) }
+    fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String {
+        "Shows a random page, which can be filtered the same way as a search.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> { own(&["rp", "rp -t keter -t scp -r >100"]) }
+
+    fn run(&mut self, opts: &Args, _: &Context, db: &mut Db) -> Outcome {
+        let conn = db.conn()?;
+        let size: i64 = pages::filter(opts, page::table.count())?.get_result(&conn)?;
+        if size == 0 {
+            return Err(NoResults)
+        }
+        let page: Page = pages::filter(opts, page::table
+            .order(page::id)
+            .offset(self.rng.gen_range(0, size))
+        )?.first(&conn)?;
+        Ok(vec![Reply(lastcreated::show_page(&page, &db.titles, &db.wiki))])
+    }
+}
+
+impl Random {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filters() {
+        let args = Random::new().spec().parse(&["tree", "-t", "scp", "-t", "keter", "-r", ">100"])
+            .unwrap();
+        assert_eq!(args.free, own(&["tree"]));
+        assert_eq!(args.opt_strs("t"), own(&["scp", "keter"]));
+        assert_eq!(args.opt_str("r"), Some(">100".to_owned()));
+        assert!(Random::new().spec().parse(&["-r", "lots"]).is_err());
+        assert!(Random::new().spec().parse(&["-<", "yesterday"]).is_err());
+    }
+
+    #[test] #[ignore]
+    fn picks_filtered_pages() {
+        let mut random = Random::new();
+        assert!(random.test_def("-f scp-173").unwrap().contains("SCP-173"));
+        match random.test_def("-f no-such-page-exists") {
+            Err(NoResults) => (),
+            other          => panic!("Expected no results, got {:?}", other)
+        }
+    }
+}
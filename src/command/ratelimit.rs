@@ -0,0 +1,103 @@
+use super::*;
+use crate::db::{RateLimit, ratelimit, scoped, upsert};
+
+pub struct RateLimits;
+
+impl Command for RateLimits {
+    fn cmds(&self) -> Vec<String> {
+        own(&["ratelimit"])
+    }
+    fn spec(&self) -> Spec {
+        Spec::new()
+            .optional("per user", Kind::Number)
+            .optional("per channel", Kind::Number)
+            .sub("reset", Spec::new())
+    }
+    fn auth(&self) -> Auth { Op }
+    fn description(&self) -> String {
+        "Shows or changes how many commands users can use per minute in this channel.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> { own(&["ratelimit 5 20"]) }
+
+    fn run(&mut self, args: &Args, ctx: &Context, db: &mut Db) -> Outcome {
+        let config = match (args.sub, args.get("per user"), args.get("per channel")) {
+            (Some(_), _, _)    => RateLimit::new(&ctx.network, &ctx.channel),
+            (None, None, None) => return Ok(vec![Reply(show(&db.limit(ctx)).into())]),
+            (None, Some(user), Some(chan)) => {
+                let per_user: i32 = user.parse().map_err(|_| too_high())?;
+                let per_channel: i32 = chan.parse().map_err(|_| too_high())?;
+                if per_user < 1 || per_channel < per_user {
+                    return Err(BadArgs(
+                        "Each user should get at least 1, and no more than the channel.".into()
+                    ))
+                }
+                RateLimit {
+                    channel: ctx.channel.to_owned(),
+                    per_user,
+                    per_channel,
+                    network: ctx.network.to_owned()
+                }
+            },
+            _ => return Err(BadArgs("Give both limits, or neither.".into()))
+        };
+        self.save(&config, db)?;
+        let reply = show(&config);
+        db.limits.insert(scoped(&config.network, &config.channel), config);
+        Ok(vec![Reply(reply.into())])
+    }
+}
+
+impl RateLimits {
+    fn save(&self, config: &RateLimit, db: &Db) -> Result<(), Error> {
+        diesel::insert_into(ratelimit::table)
+            .values(config)
+            .on_conflict((ratelimit::network, ratelimit::channel))
+            .do_update()
+            .set((upsert(ratelimit::per_user), upsert(ratelimit::per_channel)))
+            .execute(&db.conn()?)?;
+        Ok(())
+    }
+}
+
+fn too_high() -> Error {
+    BadArgs("That limit is too high.".into())
+}
+
+fn show(config: &RateLimit) -> String {
+    format!(
+        "Each user can use {} commands per minute, and the channel can use {}. \
+        Commands that search other sites count as {}.",
+        config.per_user, config.per_channel, NETWORK_COST
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_defaults() {
+        assert_eq!(
+            RateLimits.test_def("").unwrap(), 
+            "Each user can use 10 commands per minute, and the channel can use 30. \
+            Commands that search other sites count as 3."
+        );
+    }
+
+    #[test]
+    fn rejects_bad_limits() {
+        assert!(RateLimits.test_def("0 10").is_err());
+        assert!(RateLimits.test_def("10 5").is_err());
+        assert!(RateLimits.test_def("x 5").is_err());
+        assert!(RateLimits.test_def("5").is_err());
+    }
+
+    #[test] #[ignore]
+    fn sets_limits() {
+        let mut db = Db::default();
+        let ctx = Context::default();
+        RateLimits.test("5 20", &ctx, &mut db).unwrap();
+        assert_eq!(db.limit(&ctx).per_user, 5);
+        RateLimits.test("reset", &ctx, &mut db).unwrap();
+    }
+}
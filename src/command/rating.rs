@@ -0,0 +1,66 @@
+use std::time::SystemTime;
+
+use super::*;
+use crate::db::Page;
+use crate::util;
+
+pub struct Rating;
+
+impl Command for Rating {
+    fn cmds(&self) -> Vec<String> {
+        own(&["rating", "rate"])
+    }
+    fn spec(&self) -> Spec { Spec::new().rest("page name or title") }
+    fn auth(&self) -> Auth { Anyone }
+    fn cost(&self) -> u32 { NETWORK_COST }
+    fn description(&self) -> String {
+        "Looks up a page's current rating and how it has changed since the last sync.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> { own(&["rating scp-173"]) }
+
+    fn run(&mut self, args: &Args, _: &Context, db: &mut Db) -> Outcome {
+        let page = page::find(args.text("page name or title"), db)?;
+        let live = db.wiki.rate(&page.id, &db.client).ok_or(NoResults)?;
+        Ok(vec![Reply(show(&db.title(&page), live, &page))])
+    }
+    fn spawn(&mut self, args: &Args, _: &Context, db: &Db) -> Option<Job<Outcome>> {
+        let page = match page::find(args.text("page name or title"), db) {
+            Err(e)   => return Some(done(Err(e))),
+            Ok(page) => page
+        };
+        let (title, wiki, cli) = (db.title(&page), db.wiki.clone(), db.client.clone());
+        Some(Box::new(move || {
+            let live = wiki.rate(&page.id, &cli).ok_or(NoResults)?;
+            Ok(vec![Reply(show(&title, live, &page))])
+        }))
+    }
+}
+
+fn show(title: &str, live: i64, page: &Page) -> Text {
+    Text::new()
+        .bold(title)
+        .plain(" has a rating of ")
+        .bold(util::rating(live))
+        .plain(format!(" ({}).", delta(live - i64::from(page.rating), page.updated)))
+}
+
+fn delta(change: i64, since: SystemTime) -> String {
+    if change == 0 {
+        format!("no change since {} ago", util::ago(since))
+    } else {
+        format!("{} since {} ago", util::rating(change), util::ago(since))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_deltas() {
+        let now = SystemTime::now();
+        assert_eq!(delta(12, now), "+12 since a few seconds ago");
+        assert_eq!(delta(-3, now), "-3 since a few seconds ago");
+        assert_eq!(delta(0, now), "no change since a few seconds ago");
+    }
+}
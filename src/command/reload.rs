@@ -6,13 +6,15 @@ impl Command for Reload {
     fn cmds(&self) -> Vec<String> {
         own(&["reload"])
     }
-    fn usage(&self) -> String { "".to_owned() }
-    fn fits(&self, size: usize) -> bool { size == 0 }
+    fn spec(&self) -> Spec { Spec::new() }
     fn auth(&self) -> Auth { Owner }
+    fn description(&self) -> String {
+        "Reloads the bot's data from its database and supplemental webpages.".to_owned()
+    }
 
-    fn run(&mut self, _: &[&str], _: &Context, db: &mut Db) -> Outcome {
+    fn run(&mut self, _: &Args, _: &Context, db: &mut Db) -> Outcome {
         db.reload().map_err(Throw)?;
-        Ok(vec![Action("reloads its database.".to_owned())])
+        Ok(vec![Action("reloads its database.".into())])
     }
 }
 
@@ -14,18 +14,21 @@ impl Command for Roll {
     fn cmds(&self) -> Vec<String> {
         own(&["roll", "throw"])
     }
-    fn usage(&self) -> String { 
-        "<dice>. Examples: [\x02roll\x02 d20 + 4 - 2d6!], [\x02roll\x02 3dF-2], [\x02roll\x02 2d6>3 - 1d4].".to_owned() 
-    }
-    fn fits(&self, size: usize) -> bool { size >= 1 }
+    fn spec(&self) -> Spec { Spec::new().rest("dice") }
     fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String { "Rolls dice.".to_owned() }
+    fn examples(&self) -> Vec<String> {
+        own(&["roll d20 + 4 - 2d6!", "roll 3dF", "roll 2d6>3 + 10"])
+    }
 
-    fn run(&mut self, args: &[&str], _: &Context, _: &mut Db) -> Outcome {
-        let content = args.join(" ");
-        match self.throw(&content) {
+    fn run(&mut self, args: &Args, _: &Context, _: &mut Db) -> Outcome {
+        let content = args.text("dice");
+        match self.throw(content) {
             Err(NoResults) => Err(InvalidArgs),
             Err(err)       => Err(err),
-            Ok(roll)       => Ok(vec![Reply(format!("\x02{}\x02 (rolled {})", roll, content))])
+            Ok(roll)       => Ok(vec![Reply(
+                Text::new().bold(roll).plain(format!(" (rolled {})", content))
+            )])
         }
     }
 }
@@ -1,87 +1,142 @@
 use chrono::{DateTime, Utc};
 use diesel::dsl::sum;
-use getopts::{Matches, Options};
+use diesel::pg::Pg;
+use diesel::query_builder::BoxedSelectStatement;
+use diesel::query_dsl::methods::BoxedDsl;
+use hashbrown::HashMap;
 
 use super::*;
 use crate::db::{Conn, Page, page, pages};
 use crate::util;
 
-pub struct Search {
-    opts: Options
-}
+/// The most pages taken from the index. The match count still covers every page it found.
+const RESULTS: usize = 500;
+
+/// Page ids from the full-text index, best match first, and how many pages it matched in all;
+/// or `None` if the index wasn't used.
+type Ranked = Option<(Vec<String>, usize)>;
+
+pub struct Search;
 
 impl Command for Search {
     fn cmds(&self) -> Vec<String> {
         own(&["search", "searc", "sear", "sea", "s"]) // but not se(en)
     }
-    fn usage(&self) -> String { "<query> [-a <author>] [-t <tag>] [-t <another>] [-< <before MM-DD-YYYY>] [-> <after MM-DD-YYYY>] [-e <exclude>] [-e <another>] [-u]".to_owned() }
-    fn fits(&self, size: usize) -> bool { size >= 1 }
+    fn spec(&self) -> Spec { pages::sorting(pages::options(Spec::new().optional_rest("query"))) }
     fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String {
+        "Searches the wiki for pages by title, author, tag, date or rating.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> {
+        own(&["s tree -t scp", "s -a Jabyrwock -r >50 --sort rating"])
+    }
 
-    fn run(&mut self, args: &[&str], _: &Context, db: &mut Db) -> Outcome {
-        let opts = self.opts.parse(args)?;
+    fn run(&mut self, args: &Args, _: &Context, db: &mut Db) -> Outcome {
+        if args.is_empty() {
+            return Err(InvalidArgs)
+        }
+        let mut opts = args.clone();
+        let ranked = if opts.free.is_empty() || db.index.is_empty() {
+            None
+        } else {
+            let query = opts.free.join(" ");
+            // Quoted phrases still have to appear in the title as a whole.
+            opts.free.retain(|x| x.contains(' '));
+            Some(db.index.search(&query, RESULTS).map_err(Throw)?)
+        };
         let conn = db.conn()?;
 
-        let size = pages::filter(&opts, page::table.count())?.get_result(&conn)?;
+        let found = filter(&ranked, &opts, page::table.count())?.get_result(&conn)?;
+        // If no other option ruled out any of the best matches, the rest of them count too.
+        let size = match &ranked {
+            Some((ids, total)) if found == ids.len() as i64 => *total as i64,
+            _                                               => found
+        };
 
         match size {
             0 => Err(NoResults),
-            1 => Ok(vec![Reply(self.show_result(&opts, &conn, db)?)]),
+            1 => Ok(vec![Reply(self.show_result(&ranked, &opts, &conn, db)?)]),
             _ if opts.opt_present("u") => {
-                let authors = pages::filter(&opts, page::table
+                let authors = filter(&ranked, &opts, page::table
                     .select(page::created_by)
                     .distinct_on(page::created_by)
                 )?.execute(&conn)?;
-                let votes: Option<i64> = pages::filter(&opts, page::table
+                let votes: Option<i64> = filter(&ranked, &opts, page::table
                     .select(sum(page::rating))
                 )?.get_result(&conn)?;
                 let rating = votes.unwrap_or(0);
-                let avg = rating / size;
-                let earliest: DateTime<Utc> = pages::filter(&opts, page::table
+                let avg = rating / found;
+                let earliest: DateTime<Utc> = filter(&ranked, &opts, page::table
                     .select(page::created_at)
                     .order(page::created_at.asc())
                 )?.get_result(&conn)?;
-                let latest: DateTime<Utc> = pages::filter(&opts, page::table
+                let latest: DateTime<Utc> = filter(&ranked, &opts, page::table
                     .select(page::created_at)
                     .order(page::created_at.desc())
                 )?.get_result(&conn)?;
-                let highest: Page = pages::filter(&opts, page::table
+                let highest: Page = filter(&ranked, &opts, page::table
                     .order(page::rating.desc())
                 )?.first(&conn)?;
-                Ok(vec![Reply(format!(
-                    "Found \x02{}\x02 pages by \x02{}\x02 authors. They have a total rating of \x02{}\x02, with an average of \x02{}\x02. The pages were created between {} ago and {} ago. The highest rated page is \x02{}\x02 at \x02{}\x02.",
-                    size, authors, util::rating(rating), util::rating(avg), 
-                    util::ago(earliest), util::ago(latest),
-                    db.title(&highest), util::rating(i64::from(highest.rating))
-                ))])
+                Ok(vec![Reply(Text::new()
+                    .plain("Found ").bold(found)
+                    .plain(" pages by ").bold(authors)
+                    .plain(" authors. They have a total rating of ").bold(util::rating(rating))
+                    .plain(", with an average of ").bold(util::rating(avg))
+                    .plain(format!(
+                        ". The pages were created between {} ago and {} ago.",
+                        util::ago(earliest), util::ago(latest)
+                    ))
+                    .plain(" The highest rated page is ").bold(db.title(&highest))
+                    .plain(" at ").bold(util::rating(i64::from(highest.rating)))
+                    .plain(".")
+                )])
             },
-            _ => Err(Ambiguous(size, 
-                pages::filter(&opts, page::table
-                    .select(page::title)
-                    .order(page::created_at.desc())
-                    .limit(20)
-                )?.load(&conn)?
-            ))
+            _ => Err(Ambiguous(size, titles(&ranked, &opts, &conn)?))
         }
     }
 }
 
 impl Search {
-    #[inline]
-    pub fn new() -> Self {
-        Self { opts: pages::options() }
+    fn show_result(&self, ranked: &Ranked, opts: &Args, conn: &Conn, db: &mut Db) 
+    -> Result<Text, Error> {
+        let page: Page = filter(ranked, opts, page::table)?.first(conn)?;
+        Ok(Text::new()
+            .bold(db.title(&page))
+            .plain(format!(" (written {} ago by {}; ", util::ago(page.created_at), page.created_by))
+            .bold(util::rating(db.wiki.rate(&page.id, &db.client).ok_or(NoResults)?))
+            .plain(") - ")
+            .link(format!("http://{}/{}", db.wiki.root, page.id))
+        )
     }
+}
+
+fn filter<'a, B, T>(ranked: &Ranked, opts: &Args, q: B)
+-> Result<BoxedSelectStatement<'a, T, page::table, Pg>, Error> 
+where B: QueryDsl + BoxedDsl<'a, Pg, Output = BoxedSelectStatement<'a, T, page::table, Pg>> {
+    let query = pages::filter(opts, q)?;
+    Ok(match ranked {
+        None           => query,
+        Some((ids, _)) => query.filter(page::id.eq_any(ids.to_owned()))
+    })
+}
 
-    fn show_result(&self, opts: &Matches, conn: &Conn, db: &mut Db) -> Result<String, Error> {
-        let page: Page = pages::filter(opts, page::table)?.first(conn)?;
-        Ok(format!(
-            "\x02{}\x02 (written {} ago by {}; \x02{}\x02) - http://{}/{}",
-            db.title(&page),
-            util::ago(page.created_at),
-            page.created_by,
-            util::rating(db.wiki.rate(&page.id, &db.client).ok_or(NoResults)?),
-            db.wiki.root,
-            page.id
-        ))
+fn titles(ranked: &Ranked, opts: &Args, conn: &Conn) -> Result<Vec<String>, Error> {
+    match ranked {
+        Some((ids, _)) if !pages::sorted(opts) => {
+            let mut results: Vec<(String, String)> = filter(ranked, opts, page::table
+                .select((page::id, page::title))
+            )?.load(conn)?;
+            let rank: HashMap<&str, usize> = ids
+                .iter()
+                .enumerate()
+                .map(|(i, id)| (id.as_str(), i))
+                .collect();
+            results.sort_by_key(|(id, _)| rank.get(id.as_str()).cloned());
+            Ok(results.into_iter().map(|(_, title)| title).collect())
+        },
+        _ => Ok(pages::sort(opts, filter(ranked, opts, page::table
+            .select(page::title)
+            .limit(RESULTS as i64)
+        )?)?.load(conn)?)
     }
 }
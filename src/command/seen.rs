@@ -7,11 +7,20 @@ impl Command for Seen {
     fn cmds(&self) -> Vec<String> {
         own(&["seen", "se"])
     }
-    fn usage(&self) -> String { "[#<channel>] [-f|-t] <user>".to_owned() }
-    fn fits(&self, size: usize) -> bool { size >= 1 }
+    fn spec(&self) -> Spec {
+        Spec::new()
+            .optional("channel", Kind::Channel)
+            .param("user", Kind::Word)
+            .flag("f", "first")
+            .flag("t", "total")
+    }
     fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String {
+        "Shows the last thing a user said and when they said it.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> { own(&["seen Jabyrwock", "seen -t Jabyrwock"]) }
 
-    fn run(&mut self, args: &[&str], ctx: &Context, db: &mut Db) -> Outcome {
+    fn run(&mut self, args: &Args, ctx: &Context, db: &mut Db) -> Outcome {
         Ok(vec![Reply(search(args, ctx,  db)?)])
     }
 }
@@ -23,46 +32,42 @@ pub enum Mode {
     Total
 }
 
-pub fn mode(s: &str) -> Option<Mode> {
-    match s {
-        "-f"      => Some(Mode::First),
-        "-t"      => Some(Mode::Total),
-        "--first" => Some(Mode::First),
-        "--total" => Some(Mode::Total),
-        _         => None
+pub fn mode(args: &Args) -> Result<Mode, Error> {
+    match (args.opt_present("first"), args.opt_present("total")) {
+        (false, false) => Ok(Mode::Regular),
+        (true, false)  => Ok(Mode::First),
+        (false, true)  => Ok(Mode::Total),
+        (true, true)   => Err(BadArgs("Pick either -f or -t, not both.".into()))
     }
 }
 
-fn search(args_im: &[&str], ctx: &Context, db: &Db) -> Result<String, Error> {
-    let mut args = args_im.to_owned();
-    let mode = match util::pop_filter(&mut args, |x| x.starts_with('-')) {
-        None       => Mode::Regular,
-        Some(flag) => mode(flag).ok_or(InvalidArgs)?
-    };
-    let channel = util::pop_filter(&mut args, |x| x.starts_with('#'))
-        .map(ToOwned::to_owned)
-        .unwrap_or_else(||ctx.channel.to_owned());
-    match args.as_slice() {
-        [nick] => find(&nick, &channel, &mode, db).ok_or(NoResults),
-        _      => Err(InvalidArgs)
-    }
+fn search(args: &Args, ctx: &Context, db: &Db) -> Result<Text, Error> {
+    let mode = mode(args)?;
+    let channel = args.get("channel").unwrap_or(&ctx.channel);
+    find(args.text("user"), &ctx.network, channel, &mode, db).ok_or(NoResults)
 }
 
-fn find(nick: &str, channel: &str, mode: &Mode, db: &Db) -> Option<String> {
-    let seen = db.get_seen(channel, nick).ok()?;
+fn find(nick: &str, network: &str, channel: &str, mode: &Mode, db: &Db) -> Option<Text> {
+    let seen = db.get_seen(network, channel, nick).ok()?;
+    let text = Text::new();
     match mode {
-        Mode::First => Some(format!(
-            "I first saw \x02{}\x02 {} ago, saying: {}", 
-            nick, util::ago(seen.first_time), seen.first
-        )),
-        Mode::Regular => Some(format!(
-            "I last saw \x02{}\x02 {} ago, saying: {}",
-            nick, util::ago(seen.latest_time), seen.latest
-        )),
-        Mode::Total => Some(format!(
-            "I have seen \x02{}\x02 total message{} from \x02{}\x02.",
-            seen.total, if seen.total != 1 { "s" } else { "" }, nick
-        ))
+        Mode::First => Some(text
+            .plain("I first saw ")
+            .bold(nick)
+            .plain(format!(" {} ago, saying: {}", util::ago(seen.first_time), seen.first))
+        ),
+        Mode::Regular => Some(text
+            .plain("I last saw ")
+            .bold(nick)
+            .plain(format!(" {} ago, saying: {}", util::ago(seen.latest_time), seen.latest))
+        ),
+        Mode::Total => Some(text
+            .plain("I have seen ")
+            .bold(seen.total)
+            .plain(format!(" total message{} from ", if seen.total != 1 { "s" } else { "" }))
+            .bold(nick)
+            .plain(".")
+        )
     }
 }
 
@@ -70,6 +75,12 @@ fn find(nick: &str, channel: &str, mode: &Mode, db: &Db) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::text::Format;
+
+    fn search(args: &[&str], ctx: &Context, db: &Db) -> Result<String, Error> {
+        super::search(&Seen.spec().parse(args)?, ctx, db).map(|x| x.render(Format::Irc))
+    }
+
     fn ctx_test() -> Context {
         Context::mock("#@", "@A")
     }
@@ -120,6 +131,14 @@ mod tests {
     }
 
 
+    #[test] #[ignore]
+    fn keeps_networks_apart() {
+        let ctx = ctx_test();
+        let mut other = ctx.clone();
+        other.network = "synirc".to_owned();
+        assert!(search(&[&ctx.nick], &other, &db_test()).is_err());
+    }
+
     #[test] #[ignore]
     fn privmsg_is_none() {
         let ctx = Context::mock("@A", "@A");
@@ -0,0 +1,78 @@
+use super::*;
+use crate::args::quote;
+use crate::util;
+
+/// The name used to turn off suggestions for unknown commands in a channel.
+pub const CANON: &str = "suggest";
+const LIMIT: usize = 5;
+
+impl Commands {
+    /// Close matches for an unknown command, each with the original arguments, so that picking
+    /// one runs it as if it had been typed correctly.
+    pub(super) fn suggest(&self, cmd: &str, args: &[&str], ctx: &Context, db: &Db, auth: Auth) 
+    -> Vec<String> {
+        let len = cmd.chars().count();
+        if len < 3 || db.silenced(ctx, CANON) {
+            return Vec::new()
+        }
+        let max = if len <= 4 { 1 } else { 2 };
+        let mut best: HashMap<&str, (usize, &str)> = HashMap::new();
+        for (key, &i) in &self.keys {
+            let canon = match self.canons.get(key) {
+                Some(canon) => canon,
+                None        => continue
+            };
+            let usable = self.stash.get(i).map_or(false, |x| {
+                db.permission(ctx, canon, x.auth()) <= auth
+            });
+            if !usable || db.silenced(ctx, canon) {
+                continue
+            }
+            let distance = util::distance(cmd, key);
+            if distance <= max {
+                let entry = best.entry(canon.as_str()).or_insert((distance, key.as_str()));
+                if (distance, key.as_str()) < *entry {
+                    *entry = (distance, key.as_str());
+                }
+            }
+        }
+        let mut matches: Vec<(usize, &str)> = best.values().cloned().collect();
+        matches.sort();
+        matches
+            .into_iter()
+            .take(LIMIT)
+            .map(|(_, key)| if args.is_empty() { key.to_owned() } else {
+                let args: Vec<String> = args.iter().map(|x| quote(x)).collect();
+                format!("{} {}", key, args.join(" "))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{GLOBAL, Silence, establish_connection};
+
+    #[test]
+    fn suggests_close_commands() {
+        let cmds = Commands::new(&establish_connection());
+        let db = Db::default();
+        let suggest = |cmd: &str| cmds.suggest(cmd, &["tree"], &Context::default(), &db, Anyone);
+        assert_eq!(suggest("serch"), own(&["search tree"]));
+        assert!(suggest("qwertyuiop").is_empty());
+        assert!(suggest("quiz").iter().all(|x| !x.starts_with("quit")));
+    }
+
+    #[test]
+    fn can_be_disabled() {
+        let cmds = Commands::new(&establish_connection());
+        let mut db = Db::default();
+        db.silences.insert(Silence {
+            channel: GLOBAL.to_owned(),
+            command: CANON.to_owned(),
+            ..Silence::default()
+        });
+        assert!(cmds.suggest("serch", &[], &Context::default(), &db, Anyone).is_empty());
+    }
+}
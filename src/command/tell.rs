@@ -1,7 +1,7 @@
 use std::time::SystemTime;
 
 use super::*;
-use crate::db::tell;
+use crate::db::{scoped, tell};
 
 pub struct Tell;
 
@@ -9,21 +9,37 @@ impl Command for Tell {
     fn cmds(&self) -> Vec<String> {
         abbrev("tell")
     }
-    fn usage(&self) -> String { "<user> <message>".to_owned() }
-    fn fits(&self, size: usize) -> bool { size >= 2 }
+    fn spec(&self) -> Spec { Spec::new().param("user", Kind::Word).rest("message") }
     fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String {
+        "Passes a message to a user the next time they speak.".to_owned()
+    }
+    fn examples(&self) -> Vec<String> { own(&["tell Jabyrwock Check your memos."]) }
 
-    fn run(&mut self, args: &[&str], ctx: &Context, db: &mut Db) -> Outcome {
-        let (nick, msg) = args.split_first().unwrap();
+    fn run(&mut self, args: &Args, ctx: &Context, db: &mut Db) -> Outcome {
+        let nick = args.text("user");
         let target = nick.to_lowercase();
         let tell = db::Tell {
             sender:  ctx.nick.to_owned(),
             target:  target.to_owned(),
             time:    SystemTime::now(),
-            message: msg.join(" ")
+            message: args.text("message").to_owned(),
+            network: ctx.network.to_owned()
         };
         diesel::insert_into(tell::table).values(&tell).execute(&db.conn()?)?;
-        db.tells.insert(target, tell);
-        Ok(vec![Action(format!("writes down {}'s message for {}.", &ctx.nick, nick))])
+        db.tells.insert(scoped(&ctx.network, &target), tell);
+        Ok(vec![Action(format!("writes down {}'s message for {}.", &ctx.nick, nick).into())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_quotes_in_messages() {
+        let args = Tell.spec().parse_message(r#"Jabyrwock The "SCP" in 5" font"#).unwrap();
+        assert_eq!(args.text("user"), "Jabyrwock");
+        assert_eq!(args.text("message"), r#"The "SCP" in 5" font"#);
     }
 }
@@ -4,6 +4,9 @@ use serde_json::{Map, Value};
 use super::*;
 use crate::util;
 
+/// How long a reply can get before the summary is cut short.
+const CHARACTER_LIMIT: usize = 300;
+
 const SEARCH_URL: &str = 
 "https://en.wikipedia.org/w/api.php?format=json\
 &formatversion=2&action=query&list=search&srlimit=1&srprop=&srsearch=";
@@ -12,6 +15,7 @@ const ENTRY_URL: &str =
 "https://en.wikipedia.org/w/api.php?format=json\
 &action=query&prop=extracts|links&pllimit=100&exintro&explaintext&redirects=1&pageids=";
 
+#[derive(Clone)]
 pub struct Wikipedia {
     parens: Regex
 }
@@ -20,12 +24,19 @@ impl Command for Wikipedia {
     fn cmds(&self) -> Vec<String> {
         abbrev("wikipedia")
     }
-    fn usage(&self) -> String { "<query>".to_owned() }
-    fn fits(&self, size: usize) -> bool { size >= 1 }
+    fn spec(&self) -> Spec { Spec::new().rest("query") }
     fn auth(&self) -> Auth { Anyone }
+    fn cost(&self) -> u32 { NETWORK_COST }
+    fn description(&self) -> String { "Looks up an article on Wikipedia.".to_owned() }
+    fn examples(&self) -> Vec<String> { own(&["w Monty Oum"]) }
 
-    fn run(&mut self, args: &[&str], _: &Context, db: &mut Db) -> Outcome {
-        Ok(vec![Reply(self.search(&args.join(" "), &db.client)?)])
+    fn run(&mut self, args: &Args, _: &Context, db: &mut Db) -> Outcome {
+        Ok(vec![Reply(self.search(args.text("query"), &db.client)?)])
+    }
+    fn spawn(&mut self, args: &Args, _: &Context, db: &Db) -> Option<Job<Outcome>> {
+        let query = args.text("query").to_owned();
+        let (wikipedia, cli) = (self.clone(), db.client.clone());
+        Some(Box::new(move || Ok(vec![Reply(wikipedia.search(&query, &cli)?)])))
     }
 }
 
@@ -43,7 +54,7 @@ impl Wikipedia {
         self.parens.replace_all(&s.replace("(listen)", ""), "").replace("  ", " ")
     }
     
-    fn search(&self, query: &str, cli: &reqwest::Client) -> Result<String, Error> {
+    fn search(&self, query: &str, cli: &reqwest::Client) -> Result<Text, Error> {
         let searches = serde_json::from_reader(
             cli.get(&format!("{}{}", SEARCH_URL, encode(query))).send()?
         )?;
@@ -56,7 +67,7 @@ impl Wikipedia {
             .ok_or_else(||ParseErr(err_msg("Unable to parse entry")))?
     }
   
-    fn get_entry(&self, page: u64, json: &Value) -> Option<Result<String, Error>> {
+    fn get_entry(&self, page: u64, json: &Value) -> Option<Result<Text, Error>> {
         let result = json
             .as_object()?
             .get("query")?
@@ -74,14 +85,12 @@ impl Wikipedia {
                 return Some(Err(Ambiguous(0, disambig)))
             }
         }
-        Some( Ok(
-            util::trim(&format!(
-                "{} \x02{}\x02: {}", 
-                format!("https://en.wikipedia.org/wiki/{}", encode(title)), 
-                title, 
-                self.clean(&extract.replace("\n", " "))
-            ))
-        ) )
+        let url = format!("https://en.wikipedia.org/wiki/{}", encode(title));
+        let summary = util::trim(
+            &self.clean(&extract.replace("\n", " ")),
+            CHARACTER_LIMIT.saturating_sub(url.len() + title.len() + 3)
+        );
+        Some(Ok(Text::new().link(url).plain(" ").bold(title).plain(": ").plain(summary)))
     }
 }
 
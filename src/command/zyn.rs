@@ -6,12 +6,12 @@ impl Command for Zyn {
     fn cmds(&self) -> Vec<String> {
         abbrev("zyn")
     }
-    fn usage(&self) -> String { "".to_owned() }
-    fn fits(&self, size: usize) -> bool { size == 0 }
+    fn spec(&self) -> Spec { Spec::new() }
     fn auth(&self) -> Auth { Anyone }
+    fn description(&self) -> String { "Marps.".to_owned() }
 
-    fn run(&mut self, _: &[&str], _: &Context, _: &mut Db) -> Outcome {
-        Ok(vec![Reply("Marp.".to_owned())])
+    fn run(&mut self, _: &Args, _: &Context, _: &mut Db) -> Outcome {
+        Ok(vec![Reply("Marp.".into())])
     }
 }
 
@@ -3,23 +3,50 @@ use std::time::Instant;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Context {
+    /// The network the message came from, which keeps channels of the same name apart.
+    pub network: String,
     pub channel: String,
     pub nick:    String,
     pub host:    String,
+    /// The username between the `!` and `@` of the user's hostmask.
+    pub ident:   String,
     pub user:    String,
+    /// The services account the user is logged in to, if the server says.
+    pub account: Option<String>,
     pub time:    Instant
 }
 
 impl Context {
-    pub fn build(message: Message) -> Option<Context> {
+    pub fn build(message: Message, network: &str) -> Option<Context> {
+        let network = network.to_owned();
         let channel = message.response_target()?.to_lowercase();
+        let account = account(&message);
         let prefix  = message.prefix?.to_owned();
         let nick    = prefix.split('!').next()?.to_owned();
         let host    = prefix.split('@').last()?.to_owned();
+        let ident   = prefix.split('!').nth(1).and_then(|x| x.split('@').next()).unwrap_or("");
+        let ident   = ident.to_owned();
         let user    = nick.to_lowercase();
         let time    = Instant::now();
 
-        Some(Self { channel, nick, host, user, time })
+        Some(Self { network, channel, nick, host, ident, user, account, time })
+    }
+    /// A context for messages the bot sends to a channel on its own, rather than in response
+    /// to someone.
+    pub fn for_channel(network: &str, channel: &str) -> Self {
+        Context {
+            network: network.to_owned(),
+            channel: channel.to_lowercase(),
+            nick:    String::new(),
+            host:    String::new(),
+            ident:   String::new(),
+            user:    String::new(),
+            account: None,
+            time:    Instant::now()
+        }
+    }
+    pub fn hostmask(&self) -> String {
+        format!("{}!{}@{}", self.nick, self.ident, self.host)
     }
     pub fn since(&self) -> String {
         let dur = self.time.elapsed();
@@ -28,22 +55,38 @@ impl Context {
     #[cfg(test)]
     pub fn mock(channel: &str, nick: &str) -> Self {
         Context { 
+             network: String::new(),
              channel: channel.to_lowercase(),
              nick:    nick.to_owned(),
              host:    String::new(),
+             ident:   String::new(),
              user:    nick.to_lowercase(),
+             account: None,
              time:    Instant::now()
         }
     }
 }
+/// Reads the account from an `account-tag` tag, or from a join with `extended-join`, where `*`
+/// means the user isn't logged in.
+fn account(message: &Message) -> Option<String> {
+    let account = match &message.command {
+        Command::JOIN(_, Some(account), _) => account,
+        _ => message.tags.iter().flatten().find(|x| x.0 == "account")?.1.as_ref()?
+    };
+    if account == "*" { None } else { Some(account.to_lowercase()) }
+}
+
 #[cfg(test)]
 impl Default for Context {
     fn default() -> Self {
         Context { 
+             network: String::default(),
              channel: String::default(),
              nick:    String::default(),
              host:    String::default(),
+             ident:   String::default(),
              user:    String::default(),
+             account: None,
              time:    Instant::now()
         }
     }
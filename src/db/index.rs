@@ -0,0 +1,221 @@
+use diesel::query_dsl::RunQueryDsl;
+use hashbrown::HashMap;
+use multimap::MultiMap;
+use std::sync::{Arc, Mutex};
+use tantivy::{Document, Index, IndexReader, ReloadPolicy, Term};
+use tantivy::collector::{Count, TopDocs};
+use tantivy::query::{BooleanQuery, FuzzyTermQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value, STORED, STRING, TEXT};
+
+use crate::IO;
+use crate::db::{Attribution, Conn, Page, Tag, attribution, page, tag};
+
+const HEAP: usize = 50_000_000;
+
+#[derive(Clone)]
+pub struct PageIndex {
+    index:     Index,
+    reader:    IndexReader,
+    id:        Field,
+    title:     Field,
+    subtitle:  Field,
+    tags:      Field,
+    authors:   Field,
+    subtitles: Arc<Mutex<HashMap<String, String>>>
+}
+
+impl Default for PageIndex { fn default() -> Self { Self::new() } }
+
+impl PageIndex {
+    pub fn new() -> Self {
+        let mut schema = Schema::builder();
+        let id = schema.add_text_field("id", STRING | STORED);
+        let title = schema.add_text_field("title", TEXT | STORED);
+        let subtitle = schema.add_text_field("subtitle", TEXT);
+        let tags = schema.add_text_field("tags", TEXT);
+        let authors = schema.add_text_field("authors", TEXT);
+        let index = Index::create_in_ram(schema.build());
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .expect("Error creating page index reader");
+        Self {
+            index, reader, id, title, subtitle, tags, authors,
+            subtitles: Arc::new(Mutex::new(HashMap::new()))
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reader.searcher().num_docs() == 0
+    }
+
+    pub fn set_subtitle(&self, id: &str, subtitle: Option<&str>) {
+        if let Ok(mut subtitles) = self.subtitles.lock() {
+            match subtitle {
+                None    => subtitles.remove(id),
+                Some(s) => subtitles.insert(id.to_owned(), s.to_owned())
+            };
+        }
+    }
+
+    pub fn rebuild(&self, conn: &Conn) -> IO<()> {
+        let pages: Vec<Page> = page::table.load(conn)?;
+        let tags: Vec<Tag> = tag::table.load(conn)?;
+        let attributions: Vec<Attribution> = attribution::table.load(conn)?;
+        self.write(pages, tags, attributions)
+    }
+
+    fn write(&self, pages: Vec<Page>, tags: Vec<Tag>, attributions: Vec<Attribution>) -> IO<()> {
+        let tags: MultiMap<String, String> = tags
+            .into_iter()
+            .map(|x| (x.page_id, x.name))
+            .collect();
+        let authors: MultiMap<String, String> = attributions
+            .into_iter()
+            .map(|x| (x.page_id, x.user))
+            .collect();
+        let subtitles = self.subtitles.lock().map_err(|_| failure::err_msg("Poisoned lock"))?;
+
+        let mut writer = self.index.writer(HEAP)?;
+        writer.delete_all_documents()?;
+        for page in pages {
+            let mut doc = Document::default();
+            doc.add_text(self.id, &page.id);
+            doc.add_text(self.title, &page.title);
+            doc.add_text(self.authors, &page.created_by);
+            if let Some(subtitle) = subtitles.get(&page.id) {
+                doc.add_text(self.subtitle, subtitle);
+            }
+            for tag in tags.get_vec(&page.id).into_iter().flatten() {
+                doc.add_text(self.tags, tag);
+            }
+            for author in authors.get_vec(&page.id).into_iter().flatten() {
+                doc.add_text(self.authors, author);
+            }
+            writer.add_document(doc);
+        }
+        writer.commit()?;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// Returns the ids of up to `limit` pages matching every word in `query`, best match first,
+    /// along with how many pages matched in all.
+    /// If a page's id or title matches the query exactly, only that page is returned.
+    pub fn search(&self, query: &str, limit: usize) -> IO<(Vec<String>, usize)> {
+        let exact = query.trim().to_lowercase();
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for word in exact.split(|c: char| !c.is_alphanumeric()).filter(|x| !x.is_empty()) {
+            clauses.push((Occur::Must, Box::new(self.word(word))));
+        }
+        if clauses.is_empty() {
+            return Ok((Vec::new(), 0))
+        }
+
+        let searcher = self.reader.searcher();
+        let mut ids = Vec::new();
+        let (top, total) = searcher.search(
+            &BooleanQuery::from(clauses), &(TopDocs::with_limit(limit), Count)
+        )?;
+        for (_, address) in top {
+            let doc = searcher.doc(address)?;
+            let get = |field| doc.get_first(field).and_then(Value::text).map(ToOwned::to_owned);
+            if let (Some(id), Some(title)) = (get(self.id), get(self.title)) {
+                if id == exact || title.to_lowercase() == exact {
+                    return Ok((vec![id], 1))
+                }
+                ids.push(id);
+            }
+        }
+        Ok((ids, total))
+    }
+
+    fn word(&self, word: &str) -> BooleanQuery {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for &field in &[self.title, self.subtitle, self.tags, self.authors] {
+            clauses.push((Occur::Should, Box::new(TermQuery::new(
+                Term::from_field_text(field, word), IndexRecordOption::WithFreqs
+            ))));
+        }
+        let distance = typos(word);
+        if distance > 0 {
+            for &field in &[self.title, self.subtitle] {
+                clauses.push((Occur::Should, Box::new(FuzzyTermQuery::new(
+                    Term::from_field_text(field, word), distance, true
+                ))));
+            }
+        }
+        BooleanQuery::from(clauses)
+    }
+}
+
+/// Numbers and short words have to match exactly; longer words may be slightly misspelled.
+fn typos(word: &str) -> u8 {
+    if word.chars().any(|c| c.is_numeric()) {
+        0
+    } else {
+        match word.chars().count() {
+            0..=3 => 0,
+            4..=7 => 1,
+            _     => 2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn page(id: &str, title: &str) -> Page {
+        Page { id: id.to_owned(), title: title.to_owned(), ..Page::default() }
+    }
+
+    fn index() -> PageIndex {
+        let index = PageIndex::new();
+        index.set_subtitle("scp-173", Some("The Sculpture"));
+        index.write(
+            vec![
+                page("scp-173", "SCP-173"),
+                page("scp-1730", "SCP-1730"),
+                page("the-last-catastrophe", "The Last Catastrophe")
+            ],
+            vec![Tag {
+                page_id: "scp-173".to_owned(),
+                name:    "euclid".to_owned(),
+                updated: SystemTime::now()
+            }],
+            Vec::new()
+        ).expect("Error writing index");
+        index
+    }
+
+    #[test]
+    fn matches_exact_titles() {
+        assert_eq!(index().search("scp-173", 10).unwrap(), (vec!["scp-173".to_owned()], 1));
+    }
+
+    #[test]
+    fn matches_subtitles_and_tags() {
+        assert_eq!(index().search("sculpture euclid", 10).unwrap().0, vec!["scp-173".to_owned()]);
+    }
+
+    #[test]
+    fn tolerates_typos() {
+        let (ids, _) = index().search("catastrohpe", 10).unwrap();
+        assert_eq!(ids, vec!["the-last-catastrophe".to_owned()]);
+    }
+
+    #[test]
+    fn empty_query_is_empty() {
+        assert_eq!(index().search(" - ", 10).unwrap(), (Vec::new(), 0));
+    }
+
+    #[test]
+    fn counts_matches_past_the_limit() {
+        let (ids, total) = index().search("scp", 1).unwrap();
+        assert_eq!(ids.len(), 1);
+        assert_eq!(total, 2);
+    }
+}
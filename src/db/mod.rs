@@ -10,23 +10,34 @@ use multimap::MultiMap;
 use r2d2::PooledConnection;
 use reqwest::Client;
 use std::borrow::ToOwned;
+use std::cmp;
 use std::iter::*;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
 use std::sync::mpsc::TryRecvError::{Empty, Disconnected};
 use std::time::SystemTime;
 
 #[macro_use] mod model_macro;
+mod index;
 mod model;
 pub mod pages;
 mod schema;
 
 use crate::{Context, IO, env, util};
-use crate::auth::Auth;
+use crate::auth::{Auth, Logins};
+use crate::choices::{ChoiceMap, Choices, Held};
+use crate::error::Error;
+use crate::limit::Limiter;
+use crate::links::Links;
 use crate::logging::*;
 use crate::local::LocalMap;
-use crate::output::Output;
+use crate::output::{Output, Response};
+use crate::text::Text;
 use crate::wikidot::Wikidot;
+use crate::worker::Workers;
 use crate::background::{Ban, DiffReceiver};
 
+pub use self::index::PageIndex;
 pub use self::model::*;
 pub use self::schema::*;
 
@@ -34,19 +45,42 @@ pub type Pool = r2d2::Pool<ConnectionManager<PgConnection>>;
 pub type Conn = PooledConnection<ConnectionManager<PgConnection>>;
 
 pub struct Db {
-    nick:      String,
+    /// The bot's nick on each network.
+    nicks:     HashMap<String, String>,
     pub owner: String,
-    owner_:    String,
+    /// The owner's services account on each network that has one.
+    owners:    HashMap<String, String>,
 
-    pub choices:   Vec<String>,
+    /// Auth levels by network and services account.
+    pub accounts:  HashMap<(String, String), Auth>,
+    /// Tag filters by network and channel.
+    pub announce:  MultiMap<(String, String), String>,
+    /// New pages waiting to be announced, by network and channel.
+    pub announcements: Vec<(String, String, Text)>,
+    pub choices:   ChoiceMap,
+    choices_r:     Receiver<(Context, Choices)>,
+    pub held:      Held,
+    held_r:        Receiver<(Context, Vec<Response>)>,
+    pub ignores:   LocalMap<Ignore>,
+    pub index:     PageIndex,
+    pub limiter:   Limiter,
+    /// Rate limits and prefixes are keyed by `scoped` network and channel.
+    pub limits:    HashMap<String, RateLimit>,
+    pub links:     Links,
+    pub logins:    Logins,
+    pub permissions: LocalMap<Permission>,
+    pub prefixes:  HashMap<String, Prefix>,
+    /// Reminders and tells are keyed by `scoped` network and nick.
     pub reminders: MultiMap<String, Reminder>,
     pub silences:  LocalMap<Silence>,
     pub tells:     MultiMap<String, Tell>,
     pub wiki:      Wikidot,
+    pub workers:   Workers,
 
     pub bans:      MultiMap<String, Ban>,
     pub bans_r:    Option<DiffReceiver<(String, Ban)>>,
-    pub titles:    HashMap<String, String>,
+    pub pages_r:   Option<DiffReceiver<String>>,
+    pub titles:    Arc<HashMap<String, String>>,
     pub titles_r:  Option<DiffReceiver<(String, String)>>,
 
     pub client:   Client,
@@ -63,20 +97,48 @@ impl Default for Db {
 impl Db {
     pub fn new(pool: Pool) -> Self {
         let owner = env::get("OWNER");
+        let wiki = Wikidot::new();
+        let (workers, choices_r, held_r) = Workers::build();
+        let networks = env::networks();
         Db {
             client:    Client::new(),
-            nick:      env::get("IRC_NICK").to_lowercase(),
-            owner_:    owner.to_lowercase(),
+            nicks:     networks
+                .iter()
+                .map(|x| {
+                    let nick = x.config.nickname.to_owned().unwrap_or_default().to_lowercase();
+                    (x.name.to_owned(), nick)
+                })
+                .collect(),
+            owners:    networks
+                .into_iter()
+                .filter_map(|x| Some((x.name, x.owner?.to_lowercase())))
+                .collect(),
             owner,
-            choices:   Vec::new(),
+            accounts:  HashMap::new(),
+            announce:  MultiMap::new(),
+            announcements: Vec::new(),
+            choices:   ChoiceMap::new(),
+            choices_r,
+            held:      Held::new(),
+            held_r,
+            ignores:   LocalMap::new(),
+            index:     PageIndex::new(),
+            limiter:   Limiter::new(),
+            limits:    HashMap::new(),
+            links:     Links::new(&wiki.root, &wiki.site),
+            logins:    Logins::new(),
+            permissions: LocalMap::new(),
+            prefixes:  HashMap::new(),
             reminders: MultiMap::new(),
             silences:  LocalMap::new(),
             tells:     MultiMap::new(),
-            wiki:      Wikidot::new(),
+            wiki,
+            workers,
 
             bans:      MultiMap::new(),
             bans_r:    None,
-            titles:    HashMap::new(),
+            pages_r:   None,
+            titles:    Arc::new(HashMap::new()),
             titles_r:  None,
 
             pool
@@ -89,19 +151,25 @@ impl Db {
     }
 
     pub fn title(&self, page: &Page) -> String {
-        match self.titles.get(&page.id) {
-            None        => page.title.to_owned(),
-            Some(title) => format!("{}: {}", page.title, title)
-        }
+        title(&self.titles, page)
     }
 
     pub fn listen(&mut self) {
+        while let Ok((ctx, choices)) = self.choices_r.try_recv() {
+            self.choose(&ctx, choices);
+        }
+        while let Ok((ctx, lines)) = self.held_r.try_recv() {
+            self.held.insert(&ctx, lines);
+        }
         if let Some(titles_r) = &self.titles_r {
             loop {
                 match titles_r.try_recv() {
                     Err(Empty)          => break,
                     Err(Disconnected)   => { self.titles_r = None; break },
-                    Ok(((k, _), false)) => { self.titles.remove(&k); },
+                    Ok(((k, _), false)) => {
+                        self.index.set_subtitle(&k, None);
+                        Arc::make_mut(&mut self.titles).remove(&k);
+                    },
                     Ok(((k, v), true))  => {
                         let title = format!("{}: {}", k.to_uppercase(), v);
                         match self.conn() {
@@ -113,7 +181,8 @@ impl Db {
                             err => err.log(trace!())
                         };
                         
-                        self.titles.insert(k, v);
+                        self.index.set_subtitle(&k, Some(&v));
+                        Arc::make_mut(&mut self.titles).insert(k, v);
                     }
                 }
             }
@@ -128,8 +197,58 @@ impl Db {
                 }
             }
         }
+        let mut added = Vec::new();
+        if let Some(pages_r) = &self.pages_r {
+            loop {
+                match pages_r.try_recv() {
+                    Err(Empty)        => break,
+                    Err(Disconnected) => { self.pages_r = None; break },
+                    Ok((_, false))    => (),
+                    Ok((k, true))     => added.push(k)
+                }
+            }
+        }
+        for k in added {
+            self.announce_page(&k).log(trace!());
+        }
+    }
+
+    /// Queues a message about a new page for every channel whose tag filters it matches.
+    /// A channel with an empty filter is told about every page.
+    fn announce_page(&mut self, id: &str) -> IO<()> {
+        let conn = self.conn()?;
+        let page: Page = page::table.find(id).first(&conn)?;
+        let tags: Vec<String> = tag::table
+            .filter(tag::page_id.eq(id))
+            .select(tag::name)
+            .load(&conn)?;
+        let message = Text::new()
+            .plain("New page: ")
+            .bold(self.title(&page))
+            .plain(format!(" by {} - ", page.created_by))
+            .link(format!("http://{}/{}", self.wiki.root, page.id));
+        for (network, channel) in self.audience(&tags) {
+            self.announcements.push((network, channel, message.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Networks and channels whose tag filters match a page with the given tags.
+    fn audience(&self, tags: &[String]) -> Vec<(String, String)> {
+        self.announce
+            .iter_all()
+            .filter(|(_, filters)| filters.iter().any(|x| x.is_empty() || tags.contains(x)))
+            .map(|(place, _)| place.to_owned())
+            .collect()
     }
     
+    /// Stores a user's latest choices.
+    pub fn choose(&mut self, ctx: &Context, choices: Choices) {
+        // `.more` would otherwise keep showing the lines of an older response.
+        self.held.remove(ctx);
+        self.choices.insert(ctx, choices);
+    }
+
     #[cfg(not(test))]
     fn retrieve<Frm, To, C, L, F>(&self, table: L, conn: &Conn, f: F) -> QueryResult<C>
     where C: FromIterator<To>, L: diesel::query_dsl::LoadQuery<PgConnection, Frm>, F: Fn(Frm) -> To {
@@ -139,11 +258,38 @@ impl Db {
     #[cfg(not(test))]
     pub fn reload(&mut self) -> IO<()> {
         let conn = self.conn()?;
+        self.accounts = account::table
+            .load::<Account>(&conn)?
+            .into_iter()
+            .filter_map(|x| match x.auth.parse() {
+                Ok(auth) => Some(((x.network.to_owned(), x.name.to_lowercase()), auth)),
+                Err(())  => {
+                    log(WARNING, &format!("Unknown auth level for {}: {}", x.name, x.auth));
+                    None
+                }
+            })
+            .collect();
+        self.announce = self.retrieve::<Announce,_,_,_,_>
+            (announce::table, &conn, |x| ((x.network, x.channel), x.tag))?;
+        self.ignores = ignore::table.load(&conn)?.into_iter().collect();
+        self.permissions = permission::table.load(&conn)?.into_iter().collect();
+        self.prefixes = prefix::table
+            .load::<Prefix>(&conn)?
+            .into_iter()
+            .map(|x| (scoped(&x.network, &x.channel), x))
+            .collect();
+        self.limits = ratelimit::table
+            .load::<RateLimit>(&conn)?
+            .into_iter()
+            .map(|x| (scoped(&x.network, &x.channel), x))
+            .collect();
+        diesel::delete(silence::table.filter(silence::until.lt(SystemTime::now())))
+            .execute(&conn)?;
         self.silences = silence::table.load(&conn)?.into_iter().collect();
         self.reminders = self.retrieve::<DbReminder,_,_,_,_>
-            (reminder::table, &conn, |x| (x.user.to_owned(), Reminder::from(x)))?;
+            (reminder::table, &conn, |x| (scoped(&x.network, &x.user), Reminder::from(x)))?;
         self.tells = self.retrieve::<DbTell,_,_,_,_>
-            (tell::table, &conn, |x| (x.target.to_owned(), Tell::from(x)))?;
+            (tell::table, &conn, |x| (scoped(&x.network, &x.target), Tell::from(x)))?;
         Ok(())
     }
     #[cfg(test)]
@@ -151,13 +297,85 @@ impl Db {
         Ok(())
     }
 
-    #[inline]
+    /// The bot's own nick on the network the message came from.
+    pub fn nick(&self, ctx: &Context) -> &str {
+        self.nicks.get(&ctx.network).map_or("", String::as_str)
+    }
+
+    pub fn prefix(&self, ctx: &Context) -> Prefix {
+        self.prefixes
+            .get(&scoped(&ctx.network, &ctx.channel))
+            .cloned()
+            .unwrap_or_else(|| Prefix::new(&ctx.network, &ctx.channel))
+    }
+
+    pub fn limit(&self, ctx: &Context) -> RateLimit {
+        self.limits
+            .get(&scoped(&ctx.network, &ctx.channel))
+            .cloned()
+            .unwrap_or_else(|| RateLimit::new(&ctx.network, &ctx.channel))
+    }
+
+    /// Charges a command to the user's and the channel's rate limits. Ops aren't limited.
+    pub fn spend(&mut self, ctx: &Context, cost: u32, auth: Auth) -> Result<(), Error> {
+        if auth < Auth::Op {
+            let limit = self.limit(ctx);
+            self.limiter.spend(ctx, cost, &limit)?;
+        }
+        Ok(())
+    }
+
+    /// The level a command needs in a channel, which ops can change from its default.
+    pub fn permission(&self, ctx: &Context, cmd: &str, default: Auth) -> Auth {
+        self.permissions
+            .get(&scoped(&ctx.network, &ctx.channel), cmd)
+            .and_then(Permission::level)
+            .unwrap_or(default)
+    }
+
+    /// Whether a command is disabled in the channel or everywhere on its network. Timed silences
+    /// stop counting once they run out, and the next reload clears them away.
+    pub fn silenced(&self, ctx: &Context, cmd: &str) -> bool {
+        [ctx.channel.as_str(), GLOBAL]
+            .iter()
+            .filter_map(|x| self.silences.get(&scoped(&ctx.network, x), cmd))
+            .any(Silence::active)
+    }
+
+    /// Accounts in the `account` table get their level everywhere on their network. Otherwise, it
+    /// comes from the user's channel modes. Nicks alone count for nothing, since anyone can take
+    /// one.
     pub fn auth<T: Output>(&self, ctx: &Context, irc: &T) -> Auth {
-        if ctx.user == self.nick || ctx.user == self.owner_ {
-            Auth::Owner
-        } else {
-            irc.auth(ctx)
+        cmp::max(self.granted(ctx), irc.auth(ctx))
+    }
+
+    /// The level the user's services account has been granted on the network, wherever they are.
+    /// The same account name on another network may belong to someone else.
+    pub fn granted(&self, ctx: &Context) -> Auth {
+        let account = match self.logins.get(ctx) {
+            None          => return Auth::Anyone,
+            Some(account) => account
+        };
+        if self.owners.get(&ctx.network).map_or(false, |x| x == account) {
+            return Auth::Owner
+        }
+        self.accounts
+            .get(&(ctx.network.to_owned(), account.to_owned()))
+            .cloned()
+            .unwrap_or(Auth::Anyone)
+    }
+
+    /// Whether the bot should pay no attention to the user in the channel or everywhere on its
+    /// network. The owner is never ignored, so a careless mask can't lock everyone out.
+    pub fn ignored(&self, ctx: &Context) -> bool {
+        if self.granted(ctx) == Auth::Owner {
+            return false
         }
+        let account = self.logins.get(ctx);
+        [ctx.channel.as_str(), GLOBAL]
+            .iter()
+            .flat_map(|x| self.ignores.in_channel(&scoped(&ctx.network, x)))
+            .any(|x| x.matches(ctx, account))
     }
 
     pub fn get_ban(&self, ctx: &Context) -> Option<String> {
@@ -169,13 +387,14 @@ impl Db {
 
     pub fn get_reminders(&mut self, ctx: &Context) -> Option<Vec<Reminder>> {
         let time = SystemTime::now();
-        let mut reminders = self.reminders.get_vec_mut(&ctx.user)?;
+        let mut reminders = self.reminders.get_vec_mut(&scoped(&ctx.network, &ctx.user))?;
         let expired = util::drain_filter(&mut reminders, |x| x.time < time);
         
         if !expired.is_empty() {
             match self.conn() {
                 Ok(conn) => diesel::delete(
                     reminder::table
+                        .filter(reminder::network.eq(&ctx.network))
                         .filter(reminder::user.eq(&ctx.user))
                         .filter(reminder::time.lt(&time))
                     )
@@ -189,11 +408,14 @@ impl Db {
     }
 
     pub fn get_tells(&mut self, ctx: &Context) -> Option<Vec<Tell>> {
-        let tells = self.tells.remove(&ctx.user)?;
+        let tells = self.tells.remove(&scoped(&ctx.network, &ctx.user))?;
         
         if !tells.is_empty() {
             match self.conn() {
-                Ok(conn) => diesel::delete(tell::table.filter(tell::target.eq(&ctx.user)))
+                Ok(conn) => diesel::delete(tell::table
+                        .filter(tell::network.eq(&ctx.network))
+                        .filter(tell::target.eq(&ctx.user))
+                    )
                     .execute(&conn)
                     .log(trace!()),
                 err => err.log(trace!())
@@ -205,8 +427,9 @@ impl Db {
 
 
     pub fn add_seen(&mut self, ctx: &Context, message: &str) -> IO<()> {
-        if ctx.channel != ctx.user && ctx.user != self.nick {
+        if ctx.channel != ctx.user && ctx.user != self.nick(ctx) {
             let seen = SeenInsert {
+                network: ctx.network.to_owned(),
                 channel: ctx.channel.to_owned(),
                 user:    ctx.user.to_owned(),
                 first:   message.to_owned(),
@@ -214,7 +437,7 @@ impl Db {
             };
             diesel::insert_into(seen::table)
                 .values(&seen)
-                .on_conflict((seen::channel, seen::user))
+                .on_conflict((seen::network, seen::channel, seen::user))
                 .do_update()
                 .set((
                     upsert(seen::latest),
@@ -226,14 +449,22 @@ impl Db {
         Ok(())
     }
 
-    pub fn get_seen(&self, channel: &str, nick: &str) -> IO<Seen> {
+    pub fn get_seen(&self, network: &str, channel: &str, nick: &str) -> IO<Seen> {
         Ok(seen::table
+            .filter(seen::network.eq(network))
             .filter(seen::channel.eq(&channel.to_lowercase()))
             .filter(seen::user.eq(&nick.to_lowercase()))
         .first(&self.conn()?)?)
     }
 }
 
+pub fn title(titles: &HashMap<String, String>, page: &Page) -> String {
+    match titles.get(&page.id) {
+        None        => page.title.to_owned(),
+        Some(title) => format!("{}: {}", page.title, title)
+    }
+}
+
 #[inline]
 pub fn upsert<T: Column + ExpressionMethods + Copy>(t: T) 
 -> impl AsChangeset<Changeset=impl QueryFragment<Pg>, Target=T::Table> {
@@ -255,3 +486,39 @@ pub fn establish_connection() -> Pool {
         .connection_timeout(std::time::Duration::new(0, 1))
         .build_unchecked(ConnectionManager::new(""))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_announcements_by_tag() {
+        let mut db = Db::default();
+        let place = |network: &str, channel: &str| (network.to_owned(), channel.to_owned());
+        db.announce.insert(place("", "#all"), String::new());
+        db.announce.insert(place("", "#tales"), "tale".to_owned());
+        db.announce.insert(place("", "#scps"), "scp".to_owned());
+        db.announce.insert(place("", "#scps"), "joke".to_owned());
+        db.announce.insert(place("synirc", "#scps"), "scp".to_owned());
+        let mut audience = db.audience(&["scp".to_owned(), "keter".to_owned()]);
+        audience.sort();
+        assert_eq!(audience, vec![place("", "#all"), place("", "#scps"), place("synirc", "#scps")]);
+        assert_eq!(db.audience(&["tale".to_owned()]).len(), 2);
+        assert_eq!(db.audience(&[]), vec![place("", "#all")]);
+    }
+
+    #[test]
+    fn keeps_tells_to_their_network() {
+        let mut db = Db::default();
+        let tell = Tell {
+            target:  "alice".to_owned(),
+            network: "other".to_owned(),
+            ..Tell::default()
+        };
+        db.tells.insert(scoped(&tell.network, &tell.target), tell);
+        let ctx = Context::mock("#site19", "Alice");
+        assert!(db.get_tells(&ctx).is_none());
+        let elsewhere = Context { network: "other".to_owned(), ..ctx };
+        assert_eq!(db.get_tells(&elsewhere).map(|x| x.len()), Some(1));
+    }
+}
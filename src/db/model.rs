@@ -5,30 +5,151 @@ use std::borrow::ToOwned;
 use std::hash::{Hash, Hasher};
 use xmlrpc::Value;
 
+use crate::{Context, util};
+use crate::auth::Auth;
 use crate::db::*;
 use crate::local::Local;
 
+/// The auth level granted to a services account, wherever its user goes on its network.
+#[table_name = "account"]
+#[derive(Insertable, Queryable)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Account {
+    pub name:    String,
+    pub auth:    String,
+    pub network: String
+}
+
+#[table_name = "announce"]
+#[derive(Insertable, Queryable)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Announce {
+    pub channel: String,
+    pub tag:     String,
+    pub network: String
+}
+
+/// Someone the bot pays no attention to. Masks starting with `$a:` match services accounts, like
+/// the extban. Masks with a `!` or `@` match hostmasks, where `*` and `?` are wildcards. Anything
+/// else is a nick.
+#[table_name = "ignore"]
+#[derive(Insertable, Queryable, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ignore {
+    pub channel: String,
+    pub mask:    String,
+    pub network: String
+}
+impl Ignore {
+    pub fn matches(&self, ctx: &Context, account: Option<&str>) -> bool {
+        if self.mask.starts_with("$a:") {
+            account == Some(&self.mask[3..])
+        } else if self.mask.contains(|c| c == '!' || c == '@') {
+            util::glob(&self.mask, &ctx.hostmask().to_lowercase())
+        } else {
+            self.mask == ctx.user
+        }
+    }
+}
+impl Local for Ignore {
+    fn channel(&self) -> String { scoped(&self.network, &self.channel) }
+    fn obj(&self)     -> String { self.mask.to_owned() }
+}
+
 #[table_name = "memo"]
 #[derive(Insertable, Queryable)]
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Memo {
     pub channel: String,
     pub user:    String,
-    pub message: String
+    pub message: String,
+    pub network: String
 }
 impl Local for Memo {
-    fn channel(&self) -> String { self.channel.to_owned() }
+    fn channel(&self) -> String { scoped(&self.network, &self.channel) }
     fn obj(&self)     -> String { self.user.to_owned() }
 }
 
+/// The auth level a command needs in a channel, in place of the command's default.
+#[table_name = "permission"]
+#[derive(Insertable, Queryable)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Permission {
+    pub channel: String,
+    pub command: String,
+    pub auth:    String,
+    pub network: String
+}
+impl Permission {
+    pub fn level(&self) -> Option<Auth> {
+        self.auth.parse().ok()
+    }
+}
+impl Local for Permission {
+    fn channel(&self) -> String { scoped(&self.network, &self.channel) }
+    fn obj(&self)     -> String { self.command.to_owned() }
+}
+
+/// How commands are invoked in a channel. Channels without a row use `Prefix::new`.
+#[table_name = "prefix"]
+#[derive(Insertable, Queryable)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Prefix {
+    pub channel:  String,
+    pub prefixes: String,
+    pub brackets: bool,
+    pub network:  String
+}
+impl Prefix {
+    pub fn new(network: &str, channel: &str) -> Self {
+        Self {
+            channel:  channel.to_owned(),
+            prefixes: "! .".to_owned(),
+            brackets: true,
+            network:  network.to_owned()
+        }
+    }
+    pub fn prefixes(&self) -> Vec<&str> {
+        self.prefixes.split_whitespace().collect()
+    }
+}
+
+/// How many commands a user and a whole channel can use per minute. Channels without a row use
+/// `RateLimit::new`.
+#[table_name = "ratelimit"]
+#[derive(Insertable, Queryable)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RateLimit {
+    pub channel:     String,
+    pub per_user:    i32,
+    pub per_channel: i32,
+    pub network:     String
+}
+impl RateLimit {
+    pub fn new(network: &str, channel: &str) -> Self {
+        Self {
+            channel:     channel.to_owned(),
+            per_user:    10,
+            per_channel: 30,
+            network:     network.to_owned()
+        }
+    }
+}
+
 model!{Reminder; DbReminder; "reminder"; {
     pub user:    String,
     pub time:    SystemTime,
-    pub message: String
+    pub message: String,
+    pub network: String
 }}
 impl Default for Reminder {
     fn default() -> Self {
-        Self { user: String::default(), time: SystemTime::now(), message: String::default() }
+        Self { 
+            user:    String::default(),
+            time:    SystemTime::now(),
+            message: String::default(),
+            network: String::default()
+        }
     }
 }
 
@@ -37,6 +158,7 @@ impl Default for Reminder {
 #[derive(Insertable, Queryable)]
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SeenInsert {
+    pub network: String,
     pub channel: String,
     pub user:    String,
     pub first:   String,
@@ -52,7 +174,8 @@ pub struct Seen {
     pub first_time:  SystemTime,
     pub latest:      String,
     pub latest_time: SystemTime,
-    pub total:       i32
+    pub total:       i32,
+    pub network:     String
 }
 impl Default for Seen {
     fn default() -> Self {
@@ -63,24 +186,42 @@ impl Default for Seen {
             first_time:  SystemTime::now(),
             latest:      String::default(),
             latest_time: SystemTime::now(),
-            total:       i32::default()
+            total:       i32::default(),
+            network:     String::default()
         }
     }
 }
 impl Local for Seen {
-    fn channel(&self) -> String { self.channel.to_owned() }
+    fn channel(&self) -> String { scoped(&self.network, &self.channel) }
     fn obj(&self)     -> String { self.user.to_owned() }
 }
 
+/// The channel name that global silences are stored under.
+pub const GLOBAL: &str = "*";
+
+/// Keys a channel by its network as well, since two networks can each have a channel by the
+/// same name.
+pub fn scoped(network: &str, channel: &str) -> String {
+    format!("{}/{}", network, channel)
+}
+
+/// A disabled command. Silences without an end time last until someone enables the command.
 #[table_name = "silence"]
 #[derive(Insertable, Queryable, Default)]
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Silence {
     pub channel: String,
-    pub command: String
+    pub command: String,
+    pub until:   Option<SystemTime>,
+    pub network: String
+}
+impl Silence {
+    pub fn active(&self) -> bool {
+        self.until.map_or(true, |x| x > SystemTime::now())
+    }
 }
 impl Local for Silence {
-    fn channel(&self) -> String { self.channel.to_owned() }
+    fn channel(&self) -> String { scoped(&self.network, &self.channel) }
     fn obj(&self)     -> String { self.command.to_owned() }
 }
 
@@ -88,7 +229,8 @@ model!{Tell; DbTell; "tell"; {
     pub target:  String,
     pub sender:  String,
     pub time:    SystemTime,
-    pub message: String
+    pub message: String,
+    pub network: String
 }}
 impl Default for Tell {
     fn default() -> Self {
@@ -96,7 +238,8 @@ impl Default for Tell {
             target:  String::default(), 
             sender:  String::default(), 
             time:    SystemTime::now(),
-            message: String::default()
+            message: String::default(),
+            network: String::default()
             }
     }
 }
@@ -3,27 +3,45 @@ use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel::query_builder::BoxedSelectStatement;
 use diesel::query_dsl::methods::BoxedDsl;
-use getopts::{Options, Matches};
+use diesel::sql_types::Text;
 
-use crate::util;
+use crate::args::{Args, Kind, Spec};
 use crate::db::{attribution, page, tag};
 use crate::error::Error;
 use crate::error::Error::*;
+use crate::text;
+use crate::util;
+
+diesel_infix_operator!(IRegex, " ~* ");
 
-pub fn options() -> Options {
-    let mut opts = Options::new();
-    opts.optmulti("e", "exclude", "Exclude page titles", "TITLES");
-    opts.optmulti("t", "tag", "Limit to certain tags", "TAGS");
-    opts.optopt("a", "author", "Limit to an author", "AUTHOR");
-    opts.optopt("<", "before", "Limit to pages published before a certain date.", "MM-DD-YYYY");
-    opts.optopt(">", "after", "Limit to pages published after a certain date.", "YYYY-MM-DD");
-    opts.optflag("u", "summary", "Summarize results.");
-    //opts.optopt("r", "rating", "Limit to a range of ratings", "SCORE"); // TODO
-    // opts.optopt("s", "strict", "Match exact words", "WORDS");
-    //opts.optopt("f", "fullname", "Match an exact full name", "TITLE")
-    opts
+const DATE_FORMAT: &str = "a date like MM-DD-YYYY";
+const RATING_FORMAT: &str = "a rating like >50, <0, 10..100 or 50";
+const SORTS: &[&str] = &["rating", "date", "title"];
+
+const DATE: Kind = Kind::Check(DATE_FORMAT, |s| util::parse_date(s).is_some());
+const RATING: Kind = Kind::Check(RATING_FORMAT, |s| parse_rating(s).is_some());
+
+/// Adds the options that filter pages.
+pub fn options(spec: Spec) -> Spec {
+    spec
+        .opt("a", "author", "author", Kind::Word)
+        .multi("t", "tag", "tag", Kind::Word)
+        .opt("<", "before", "before MM-DD-YYYY", DATE)
+        .opt(">", "after", "after MM-DD-YYYY", DATE)
+        .multi("e", "exclude", "exclude", Kind::Word)
+        .opt("r", "rating", "rating", RATING)
+        .multi("s", "strict", "word", Kind::Word)
+        .opt("f", "fullname", "fullname", Kind::Word)
 }
 
+/// Adds the options that order and summarize pages.
+pub fn sorting(spec: Spec) -> Spec {
+    spec
+        .opt("", "sort", "rating|date|title", Kind::Choice(SORTS))
+        .flag("", "asc")
+        .flag("", "desc")
+        .flag("u", "summary")
+}
 
 pub fn filter_by<'a, T>(author: &str, query: BoxedSelectStatement<'a, T, page::table, Pg>)
 -> BoxedSelectStatement<'a, T, page::table, Pg> {
@@ -37,13 +55,21 @@ pub fn filter_by<'a, T>(author: &str, query: BoxedSelectStatement<'a, T, page::t
     )
 }
 
-pub fn filter<'a, B, T>(opts: &Matches, q: B)
+pub fn filter<'a, B, T>(opts: &Args, q: B)
 -> Result<BoxedSelectStatement<'a, T, page::table, Pg>, Error> 
 where B: QueryDsl + BoxedDsl<'a, Pg, Output = BoxedSelectStatement<'a, T, page::table, Pg>> {
     let mut query = q.into_boxed();
     
     for free in &opts.free {
-        query = query.filter(page::title.ilike(format!("%{}%", free)));
+        query = query.filter(page::title.ilike(containing(free)));
+    }
+
+    for word in opts.opt_strs("s") {
+        query = query.filter(IRegex::new(page::title, strict(&word).into_sql::<Text>()));
+    }
+
+    if let Some(fullname) = opts.opt_str("f") {
+        query = query.filter(page::id.eq(fullname.to_lowercase()));
     }
 
     for tag in opts.opt_strs("t") {
@@ -55,7 +81,7 @@ where B: QueryDsl + BoxedDsl<'a, Pg, Output = BoxedSelectStatement<'a, T, page::
     }
 
     for tag in opts.opt_strs("e") {
-        query = query.filter(not(page::title.ilike(format!("%{}%", tag))));
+        query = query.filter(not(page::title.ilike(containing(&tag))));
     }
 
     
@@ -64,13 +90,146 @@ where B: QueryDsl + BoxedDsl<'a, Pg, Output = BoxedSelectStatement<'a, T, page::
     }
 
     if let Some(before) = opts.opt_str("<") {
-        let date = util::parse_date(&before).ok_or(InvalidArgs)?;
+        let date = util::parse_date(&before).ok_or_else(|| expected("-<", DATE_FORMAT, &before))?;
         query = query.filter(page::created_at.lt(date));
     }
 
     if let Some(after) = opts.opt_str(">") {
-        let date = util::parse_date(&after).ok_or(InvalidArgs)?;
+        let date = util::parse_date(&after).ok_or_else(|| expected("->", DATE_FORMAT, &after))?;
         query = query.filter(page::created_at.gt(date));
     }
+
+    if let Some(rating) = opts.opt_str("r") {
+        let (min, max) = parse_rating(&rating)
+            .ok_or_else(|| expected("-r", RATING_FORMAT, &rating))?;
+        if let Some(min) = min {
+            query = query.filter(page::rating.ge(min));
+        }
+        if let Some(max) = max {
+            query = query.filter(page::rating.le(max));
+        }
+    }
     Ok(query)
 }
+
+pub fn sorted(opts: &Args) -> bool {
+    opts.opt_present("sort") || opts.opt_present("asc") || opts.opt_present("desc")
+}
+
+/// Orders by `--sort`, newest pages first by default. Titles ascend unless `--desc` is given;
+/// ratings and dates descend unless `--asc` is given.
+pub fn sort<'a, T>(opts: &Args, query: BoxedSelectStatement<'a, T, page::table, Pg>)
+-> Result<BoxedSelectStatement<'a, T, page::table, Pg>, Error> {
+    let sort = opts.opt_str("sort").unwrap_or_else(|| "date".to_owned()).to_lowercase();
+    let asc = match (opts.opt_present("asc"), opts.opt_present("desc")) {
+        (true, true)  => Err(BadArgs("Pick either --asc or --desc, not both.".into())),
+        (true, false) => Ok(true),
+        (false, true) => Ok(false),
+        _             => Ok(sort == "title")
+    }?;
+    match (sort.as_str(), asc) {
+        ("rating", true)  => Ok(query.order(page::rating.asc())),
+        ("rating", false) => Ok(query.order(page::rating.desc())),
+        ("date", true)    => Ok(query.order(page::created_at.asc())),
+        ("date", false)   => Ok(query.order(page::created_at.desc())),
+        ("title", true)   => Ok(query.order(page::title.asc())),
+        ("title", false)  => Ok(query.order(page::title.desc())),
+        _                 => Err(expected("--sort", "rating, date or title", &sort))
+    }
+}
+
+/// Matches the error that parsing gives when a value doesn't fit its option.
+fn expected(option: &str, format: &str, arg: &str) -> Error {
+    BadArgs(text::Text::new()
+        .plain(format!("{} should be {}, not ", option, format))
+        .bold(arg)
+        .plain(".")
+    )
+}
+
+/// An ILIKE pattern that matches exactly the phrase, with no wildcards of its own.
+pub fn matching(phrase: &str) -> String {
+    phrase.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// An ILIKE pattern that matches a phrase anywhere, with no wildcards of its own.
+pub fn containing(phrase: &str) -> String {
+    format!("%{}%", matching(phrase))
+}
+
+/// A Postgres regex that only matches whole words, so "cat" won't match "Catastrophe".
+fn strict(words: &str) -> String {
+    format!("\\m{}\\M", regex::escape(words))
+}
+
+/// Parses `>50`, `<0`, `10..100`, `10..`, `..100` or `50` into inclusive bounds.
+fn parse_rating(s: &str) -> Option<(Option<i32>, Option<i32>)> {
+    let bounds = if s.starts_with('>') {
+        (Some(s[1..].parse::<i32>().ok()?.checked_add(1)?), None)
+    } else if s.starts_with('<') {
+        (None, Some(s[1..].parse::<i32>().ok()?.checked_sub(1)?))
+    } else if let Some((min, max)) = util::split_on("..", s) {
+        let bound = |x: &str| if x.is_empty() { Some(None) } else { x.parse().ok().map(Some) };
+        (bound(min)?, bound(max)?)
+    } else {
+        let exact = s.parse().ok()?;
+        (Some(exact), Some(exact))
+    };
+    match bounds {
+        (None, None)                        => None,
+        (Some(min), Some(max)) if min > max => None,
+        _                                   => Some(bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_whole_phrases() {
+        assert_eq!(containing("the end"), "%the end%");
+        assert_eq!(containing("100%_done"), "%100\\%\\_done%");
+        assert_eq!(matching("50%"), "50\\%");
+    }
+
+    #[test]
+    fn escapes_strict_words() {
+        assert_eq!(strict("cat"), "\\mcat\\M");
+        assert_eq!(strict("tale (j)"), "\\mtale \\(j\\)\\M");
+    }
+
+    #[test]
+    fn parses_ratings() {
+        assert_eq!(parse_rating(">50"), Some((Some(51), None)));
+        assert_eq!(parse_rating("<0"), Some((None, Some(-1))));
+        assert_eq!(parse_rating("-10..+100"), Some((Some(-10), Some(100))));
+        assert_eq!(parse_rating("10.."), Some((Some(10), None)));
+        assert_eq!(parse_rating("7"), Some((Some(7), Some(7))));
+    }
+
+    #[test]
+    fn rejects_malformed_ratings() {
+        for s in &["", "..", ">", "<x", "100..10", "1..2..3", "=5"] {
+            assert_eq!(parse_rating(s), None, "{}", s);
+        }
+    }
+
+    #[test]
+    fn explains_bad_values() {
+        let spec = Spec::new().opt("", "sort", "order", Kind::Word);
+        let args = spec.parse(&["--sort", "size"]).unwrap();
+        match sort(&args, page::table.into_boxed()) {
+            Err(BadArgs(why)) => assert_eq!(
+                why.render(text::Format::Plain), "--sort should be rating, date or title, not size."
+            ),
+            _ => panic!("Expected an explanation")
+        }
+        let rating = Spec::new().opt("r", "rating", "rating", Kind::Word);
+        let args = rating.parse(&["-r", "lots"]).unwrap();
+        match filter(&args, page::table) {
+            Err(BadArgs(why)) => assert!(why.render(text::Format::Plain).contains(RATING_FORMAT)),
+            _                 => panic!("Expected an explanation")
+        }
+    }
+}
@@ -1,3 +1,19 @@
+table! {
+    account (network, name) {
+        name -> Text,
+        auth -> Text,
+        network -> Text,
+    }
+}
+
+table! {
+    announce (network, channel, tag) {
+        channel -> Text,
+        tag -> Text,
+        network -> Text,
+    }
+}
+
 table! {
     attribution (page_id, user) {
         page_id -> Text,
@@ -7,10 +23,19 @@ table! {
 }
 
 table! {
-    memo (channel, user) {
+    ignore (network, channel, mask) {
+        channel -> Text,
+        mask -> Text,
+        network -> Text,
+    }
+}
+
+table! {
+    memo (network, channel, user) {
         channel -> Text,
         user -> Text,
         message -> Text,
+        network -> Text,
     }
 }
 
@@ -33,17 +58,45 @@ table! {
     }
 }
 
+table! {
+    permission (network, channel, command) {
+        channel -> Text,
+        command -> Text,
+        auth -> Text,
+        network -> Text,
+    }
+}
+
+table! {
+    prefix (network, channel) {
+        channel -> Text,
+        prefixes -> Text,
+        brackets -> Bool,
+        network -> Text,
+    }
+}
+
+table! {
+    ratelimit (network, channel) {
+        channel -> Text,
+        per_user -> Int4,
+        per_channel -> Int4,
+        network -> Text,
+    }
+}
+
 table! {
     reminder (id) {
         id -> Int4,
         user -> Text,
         time -> Timestamp,
         message -> Text,
+        network -> Text,
     }
 }
 
 table! {
-    seen (channel, user) {
+    seen (network, channel, user) {
         channel -> Text,
         user -> Text,
         first -> Text,
@@ -51,13 +104,16 @@ table! {
         latest -> Text,
         latest_time -> Timestamp,
         total -> Int4,
+        network -> Text,
     }
 }
 
 table! {
-    silence (channel, command) {
+    silence (network, channel, command) {
         channel -> Text,
         command -> Text,
+        until -> Nullable<Timestamp>,
+        network -> Text,
     }
 }
 
@@ -76,6 +132,7 @@ table! {
         sender -> Text,
         time -> Timestamp,
         message -> Text,
+        network -> Text,
     }
 }
 
@@ -89,10 +146,16 @@ table! {
 joinable!(tag -> page (page_id));
 
 allow_tables_to_appear_in_same_query!(
+    account,
+    announce,
     attribution,
+    ignore,
     memo,
     namegen,
     page,
+    permission,
+    prefix,
+    ratelimit,
     reminder,
     seen,
     silence,
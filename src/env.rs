@@ -32,12 +32,46 @@ pub fn api(prefix: &str, user: &str, key: &str) -> Option<Api> {
     })
 }
 
-pub fn irc() -> Config {
+/// The name of the network configured by the unprefixed `IRC_` variables. Records from before
+/// there were several networks belong to it.
+pub const MAIN: &str = "";
+
+/// An IRC network to connect to, and what to call it.
+pub struct Network {
+    pub name:   String,
+    pub config: Config,
+    /// The services account that always has owner access on this network. Account names only
+    /// mean something on the network they were registered on.
+    pub owner:  Option<String>
+}
+
+/// The main network, followed by any listed in `NETWORKS`. Each of those is configured like the
+/// main one, but with its name in front: `SYNIRC_IRC_SERVER`, `SYNIRC_AUTOJOIN` and so on.
+/// `OWNER` is required for the main network, but optional for the others.
+pub fn networks() -> Vec<Network> {
+    let mut networks = vec![
+        Network { name: MAIN.to_owned(), config: irc(""), owner: Some(get("OWNER")) }
+    ];
+    if let Some(names) = opt("NETWORKS") {
+        for name in names.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            let prefix = format!("{}_", name.to_uppercase());
+            networks.push(Network { 
+                name:   name.to_lowercase(),
+                config: irc(&prefix),
+                owner:  opt(&format!("{}OWNER", prefix))
+            });
+        }
+    }
+    networks
+}
+
+fn irc(prefix: &str) -> Config {
+    let var = |name: &str| get(&format!("{}{}", prefix, name));
     Config {
-        server:       Some(get("IRC_SERVER")),
-        nickname:     Some(get("IRC_NICK")),
-        password:     Some(get("IRC_PASSWORD")),
-        channels:     Some(get("AUTOJOIN").split(',').map(|x| format!("#{}", x)).collect()),
+        server:       Some(var("IRC_SERVER")),
+        nickname:     Some(var("IRC_NICK")),
+        password:     Some(var("IRC_PASSWORD")),
+        channels:     Some(var("AUTOJOIN").split(',').map(|x| format!("#{}", x.trim())).collect()),
         should_ghost: Some(true),
         #[cfg(test)]
         use_mock_connection: Some(true),
@@ -1,4 +1,5 @@
 use crate::output::Response;
+use crate::text::Text;
 pub use self::Error::*;
 
 pub type Outcome = Result<Vec<Response>, Error>;
@@ -8,9 +9,13 @@ pub enum Error {
     Unknown,
     Unauthorized,
     InvalidArgs,
+    /// Arguments that don't fit a command's spec, with an explanation of what's wrong.
+    BadArgs(Text),
     NoResults,
     ParseErr(failure::Error),
     Ambiguous(i64, Vec<String>),
+    /// Whether to tell the user they've been rate-limited, which only happens the first time.
+    RateLimited(bool),
     Throw(failure::Error)
 }
 
@@ -29,11 +34,6 @@ impl From<std::io::Error> for Error {
         NoResults
     }
 }
-impl From<getopts::Fail> for Error {
-    fn from(_: getopts::Fail) -> Self {
-        InvalidArgs
-    }
-}
 impl From<serde_json::error::Error> for Error {
     fn from(e: serde_json::error::Error) -> Self {
         ParseErr(failure::Error::from(e))
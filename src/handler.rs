@@ -3,53 +3,70 @@ use irc::proto::Command::{JOIN, PRIVMSG};
 use irc::proto::message;
 use std::borrow::ToOwned;
 use std::iter::*;
+use std::sync::mpsc::Sender;
 
-use crate::{Context, util};
-use crate::command::Commands;
-use crate::db::Db;
+use crate::{Context, args, links, util};
+use crate::choices::{Choices, MAX_RANGE, parse_range};
+use crate::command::{Commands, Run};
+use crate::db::{Db, Prefix};
 use crate::logging::*;
-use crate::output::Output;
+use crate::output::{Output, Response};
 use crate::output::Response::*;
 use crate::error::*;
+use crate::text::Text;
 
 pub const NO_RESULTS: &str = "I'm sorry, I couldn't find anything.";
-const CHARACTER_LIMIT: usize = 400;
 
-pub fn handle<O: Output>(message: message::Message, cmds: &mut Commands, irc: &O, db: &mut Db) 
--> Result<(), IrcError> {
+pub fn handle<O>(
+    message: message::Message, network: &str, cmds: &mut Commands, irc: &O, db: &mut Db
+) -> Result<(), IrcError> where O: Output + Clone + Send + 'static {
     db.listen();
     let text = message.to_string();
-    match Context::build(message.to_owned()) {
+    db.logins.acknowledge(network, &message.command);
+    match Context::build(message.to_owned(), network) {
         None      => print!("{}", text),
         Some(ctx) => {
+            db.logins.update(&ctx, &message.command);
             match message.command {
                 JOIN(_, _, _) => {
                     match db.get_ban(&ctx) {
                         None         => print!("{}", text),
                         Some(reason) => {
                             log_part(WARNING, &text);
-                            irc.respond(&ctx, Ban(reason))?;
+                            irc.respond(&ctx, Ban(reason.into()))?;
                         }
                     }
                 },
+                // Ignored users still show up in `seen`, but get nothing else.
+                PRIVMSG(_, ref msg) if db.ignored(&ctx) => {
+                    print!("{}", text);
+                    db.add_seen(&ctx, msg).log(trace!());
+                },
                 PRIVMSG(_, msg) => {
+                    let mut responses = Vec::new();
                     for reminder in db.get_reminders(&ctx).into_iter().flatten() {
-                        irc.respond(&ctx, Message(format!("Reminder: {}", reminder.message))
-                        )?;
+                        responses.push(Message(format!("Reminder: {}", reminder.message).into()));
                     }
                     for tell in db.get_tells(&ctx).into_iter().flatten() {
-                        irc.respond(&ctx, Message(format!(
-                            "From \x02{}\x02 at {}: {}", 
-                            tell.sender, util::show_time(tell.time), tell.message
-                        )))?;
+                        responses.push(Message(Text::new()
+                            .plain("From ")
+                            .bold(tell.sender)
+                            .plain(format!(" at {}: {}", util::show_time(tell.time), tell.message))
+                        ));
                     }
-                    let commands = get_commands(&msg);
+                    respond(irc, &ctx, responses, db);
+                    let prefix = db.prefix(&ctx);
+                    let (commands, bracketed) = get_commands(&msg, db.nick(&ctx), &prefix);
                     if commands.is_empty() {
                         print!("{}", text);
+                        match links::expand(&ctx, &msg, db) {
+                            Err(e)        => log(ERROR, &format!("Link expansion error: {}", e)),
+                            Ok(responses) => respond(irc, &ctx, responses, db)
+                        }
                     } else {
                         log_part(ASK, &text);
                         for command in commands {
-                            run(cmds, command, &ctx, db, irc)?
+                            run(cmds, command, bracketed, &ctx, db, irc)
                         }
                     }
                     db.add_seen(&ctx, &msg).log(trace!());
@@ -61,94 +78,268 @@ pub fn handle<O: Output>(message: message::Message, cmds: &mut Commands, irc: &O
     Ok(())
 }
 
-fn get_commands(message: &str) -> Vec<&str> {
-    match (message.chars().next(), message.get(1..)) {
-        (Some('!'), Some(xs)) => vec![xs],
-        (Some('.'), Some(xs)) => vec![xs],
-        _                     => message
-            .split('[')
-            .skip(1)
-            .filter_map(|x| x.find(']').and_then(|i| {
-                let cmd = x[..i].trim();
-                if cmd.is_empty() { None } else { Some(cmd) }
-            }))
-            .collect()
+/// Sends the new pages waiting to be announced on a network's channels. Every event flushes every
+/// network, so announcements for a quiet network aren't held up until someone speaks there.
+pub fn announce<O>(network: &str, irc: &O, db: &mut Db)
+where O: Output + Clone + Send + 'static {
+    db.listen();
+    let announcements = util::drain_filter(&mut db.announcements, |x| x.0 == network);
+    for (_, channel, announcement) in announcements {
+        let ctx = Context::for_channel(network, &channel);
+        respond(irc, &ctx, vec![Notice(announcement)], db);
     }
 }
 
-fn suggest(suggestions: &[String]) -> String {
-    if suggestions.is_empty() {
-        NO_RESULTS.to_owned()
-    } else {
-        let mut s = "Did you mean:".to_owned();
-        for (i, suggest) in suggestions.into_iter().enumerate() {
-            if s.len() + suggest.len() + 7 > CHARACTER_LIMIT {
-                return s.to_owned()
-            }
-            if i > 0 {
-                s.push_str(",");
-            }
-            s.push_str(" \x02");
-            s.push_str(&(i+1).to_string());
-            s.push_str(".\x02 ");
-            s.push_str(suggest);
+/// The commands in a message, and whether they were [bracketed] rather than called directly.
+fn get_commands<'a>(message: &'a str, nick: &str, prefix: &Prefix) -> (Vec<&'a str>, bool) {
+    if let Some(cmd) = addressed(message, nick) {
+        return (vec![cmd], false)
+    }
+    for start in prefix.prefixes() {
+        match message.get(start.len()..) {
+            Some(xs) if !xs.is_empty() && message.starts_with(start) => return (vec![xs], false),
+            _ => ()
         }
-        s.to_owned()
     }
+    if !prefix.brackets {
+        return (Vec::new(), false)
+    }
+    let commands = message
+        .split('[')
+        .skip(1)
+        .filter_map(|x| x.find(']').and_then(|i| {
+            let cmd = x[..i].trim();
+            if cmd.is_empty() { None } else { Some(cmd) }
+        }))
+        .collect();
+    (commands, true)
 }
 
-fn run<O: Output>(cmds: &mut Commands, message: &str, ctx: &Context, db: &mut Db, irc: &O) 
--> Result<(), IrcError> {
-    let (cmd, args): (String, Vec<&str>) = match util::split_on(" ", message) {
-        None         => (message.to_lowercase(), Vec::new()),
-        Some((x, y)) => (x.to_lowercase(), y.split(' ').filter(|x| !x.is_empty()).collect())
+/// Matches messages like "Tex: search foo" or "tex, search foo".
+fn addressed<'a>(message: &'a str, nick: &str) -> Option<&'a str> {
+    if message.get(..nick.len())?.to_lowercase() != nick {
+        return None
+    }
+    let rest = &message[nick.len()..];
+    match rest.chars().next() {
+        Some(':') | Some(',') => {
+            let cmd = rest[1..].trim();
+            if cmd.is_empty() { None } else { Some(cmd) }
+        },
+        _ => None
+    }
+}
+
+fn run<O>(cmds: &mut Commands, message: &str, bracketed: bool, ctx: &Context, db: &mut Db, irc: &O)
+where O: Output + Clone + Send + 'static {
+    let tokens = args::split(message);
+    let (cmd, args): (String, Vec<&str>) = match tokens.split_first() {
+        None              => return,
+        Some(((_, x), y)) => (x.to_lowercase(), y.iter().map(|(_, x)| x.as_str()).collect())
     };
-    if cmd == "showmore" || cmd == "sm" {
-        match args.as_slice() {
-            [val] => match val.parse::<usize>() {
-                Ok(i) if i > 0 => match db.choices.get(i - 1).map(ToOwned::to_owned) {
-                    None    => irc.respond(ctx, Reply("That isn't one of my options.".to_owned())),
-                    Some(x) => run(cmds, &x, ctx, db, irc)
-                },
-                _ => irc.respond(ctx, Reply(cmds.usage(&cmd)))
-            },
-            _ => irc.respond(ctx, Reply(cmds.usage(&cmd)))
+    // Whatever follows the command, as it was typed.
+    let typed = tokens.get(1).map_or("", |&(i, _)| &message[i..]);
+    // A job may have finished since the message came in, and `.sm` or `.more` should see its
+    // choices and held lines.
+    db.listen();
+    let run = if cmd == "showmore" || cmd == "sm" {
+        match show_more(cmds, &args, ctx, db, irc) {
+            None          => return,
+            Some(outcome) => Run::Ready(outcome)
         }
+    } else if bracketed && !cmds.knows(&cmd) {
+        // Brackets turn up in ordinary conversation, like "[see above]", so they don't get
+        // "Did you mean" suggestions.
+        return
     } else {
-        match cmds.run(&cmd, &args, ctx, db, irc) {
-            Ok(responses)    => {
-                for response in responses { 
-                    irc.respond(ctx, response)?;
+        cmds.run(&cmd, &args, typed, ctx, db, irc)
+    };
+    let render = Render {
+        ctx:     ctx.to_owned(),
+        usage:   cmds.usage(&cmd),
+        known:   cmds.knows(&cmd),
+        cmd,
+        message: message.to_owned(),
+        owner:   db.owner.to_owned(),
+        choices: db.workers.choices()
+    };
+    match run {
+        Run::Ready(outcome) => {
+            let (responses, choices) = render.outcome(outcome);
+            if let Some(choices) = choices {
+                db.choose(ctx, choices);
+            }
+            respond(irc, ctx, responses, db);
+        },
+        Run::Spawned(job) => db.workers.run(irc, ctx, Box::new(move || render.finish(job())))
+    }
+}
+
+/// Runs the options a user picked from their latest choices. Returns `None` once it has, since
+/// each of them responds for itself.
+fn show_more<O>(cmds: &mut Commands, args: &[&str], ctx: &Context, db: &mut Db, irc: &O) 
+-> Option<Outcome> where O: Output + Clone + Send + 'static {
+    let auth = db.auth(ctx, irc);
+    if let Err(e) = db.spend(ctx, 1, auth) {
+        return Some(Err(e))
+    }
+    Some(match args {
+        [val] => match parse_range(val) {
+            Some((from, to)) if to - from >= MAX_RANGE => 
+                Ok(vec![Reply(format!("I can only show {} options at a time.", MAX_RANGE).into())]),
+            Some((from, to)) => match db.choices.get_mut(ctx).and_then(|x| x.range(from, to)) {
+                None     => Ok(vec![Reply("That isn't one of my options.".into())]),
+                Some(xs) => {
+                    for x in xs {
+                        run(cmds, &x, false, ctx, db, irc);
+                    }
+                    return None
                 }
-                Ok(())
             },
-            Err(Unknown)     => Ok(()),
-            Err(InvalidArgs) => irc.respond(ctx, Reply(cmds.usage(&cmd))),
-            Err(NoResults)   => irc.respond(ctx, Reply(NO_RESULTS.to_owned())),
-            Err(Ambiguous(size, xs)) => {
-                db.choices.clear();
-                for x in &xs {
-                    db.choices.push(format!("{} {}", cmd, x));
-                }
-                match size {
-                    0 => irc.respond(ctx, Reply(suggest(&xs))),
-                    _ => irc.respond(ctx, Reply(format!("{} ({} total)", suggest(&xs), size)))
+            None => Err(InvalidArgs)
+        },
+        _ => Err(InvalidArgs)
+    })
+}
+
+/// Splits responses here rather than on a worker, so lines held for `.more` are stored before the
+/// next message is handled. The lines that fit still go through the channel's lane, to stay in
+/// order behind anything slow that's already running there.
+fn respond<O>(irc: &O, ctx: &Context, responses: Vec<Response>, db: &mut Db)
+where O: Output + Clone + Send + 'static {
+    let mut lines = Vec::new();
+    for response in responses {
+        let (page, held) = irc.paginate(ctx, response);
+        lines.extend(page);
+        if !held.is_empty() {
+            db.held.insert(ctx, held);
+        }
+    }
+    db.workers.respond(irc, ctx, lines);
+}
+
+/// Everything needed to turn a command's outcome into responses, which might happen on a worker.
+struct Render {
+    ctx:     Context,
+    cmd:     String,
+    message: String,
+    usage:   Text,
+    known:   bool,
+    owner:   String,
+    choices: Sender<(Context, Choices)>
+}
+
+impl Render {
+    /// On a worker, choices have to be sent back to the IRC thread to be stored.
+    fn finish(self, outcome: Outcome) -> Vec<Response> {
+        let (responses, choices) = self.outcome(outcome);
+        if let Some(choices) = choices {
+            self.choices.send((self.ctx.to_owned(), choices)).log(trace!());
+        }
+        responses
+    }
+
+    fn outcome(&self, outcome: Outcome) -> (Vec<Response>, Option<Choices>) {
+        let responses = match outcome {
+            Ok(responses)    => responses,
+            Err(Unknown)     => Vec::new(),
+            Err(InvalidArgs) => vec![Reply(self.usage.to_owned())],
+            Err(BadArgs(why)) => vec![Reply(why.plain(" ").append(self.usage.to_owned()))],
+            Err(NoResults)   => vec![Reply(NO_RESULTS.into())],
+            Err(RateLimited(warn)) => {
+                log(WARNING, &format!("Rate-limited {}: {}", self.ctx.nick, self.cmd));
+                if warn {
+                    vec![Reply("You're using commands too quickly. Please wait a bit.".into())]
+                } else {
+                    Vec::new()
                 }
             },
+            Err(Ambiguous(size, xs)) => {
+                // Suggestions for an unknown command are whole commands.
+                let cmd = if self.known { self.cmd.as_str() } else { "" };
+                let mut choices = Choices::new(cmd, xs).with_total(size);
+                let suggest = choices.next().unwrap_or_else(|| NO_RESULTS.into());
+                return (vec![Reply(suggest)], Some(choices))
+            },
             Err(Unauthorized) => {
-                log(WARNING, &format!("{} used an unauthorized command: {}", ctx.nick, cmd)); 
-                Ok(()) 
+                log(WARNING, &format!(
+                    "{} used an unauthorized command: {}", self.ctx.nick, self.cmd
+                )); 
+                Vec::new()
             },
             Err(ParseErr(e)) => {
-                log(INFO, &format!("Parse error for '{}': {}", message, e));
-                irc.respond(ctx, Reply(NO_RESULTS.to_owned()))
+                log(INFO, &format!("Parse error for '{}': {}", self.message, e));
+                vec![Reply(NO_RESULTS.into())]
             },
             Err(Throw(e)) => {
-                log(ERROR, &format!("Unhandled error for '{}': {}", message, e));
-                irc.respond(ctx, Reply(
-                        format!("Something went wrong. Please let {} know.", db.owner)
-                ))
+                log(ERROR, &format!("Unhandled error for '{}': {}", self.message, e));
+                vec![Reply(format!("Something went wrong. Please let {} know.", self.owner).into())]
             }
-        }
+        };
+        (responses, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_connection;
+    use crate::output::Offline;
+
+    fn commands<'a>(message: &'a str, prefix: &Prefix) -> Vec<&'a str> {
+        get_commands(message, "tex", prefix).0
+    }
+
+    #[test]
+    fn parses_default_prefixes() {
+        let prefix = Prefix::new("", "#site19");
+        assert_eq!(commands(".w Enron", &prefix), vec!["w Enron"]);
+        assert_eq!(commands("!w Enron", &prefix), vec!["w Enron"]);
+        assert_eq!(commands("the new [w Enron] and [g x]", &prefix), vec!["w Enron", "g x"]);
+        assert!(commands("hello", &prefix).is_empty());
+    }
+
+    #[test]
+    fn parses_addressed() {
+        let prefix = Prefix::new("", "#site19");
+        assert_eq!(commands("Tex: search foo", &prefix), vec!["search foo"]);
+        assert_eq!(commands("tex, search foo", &prefix), vec!["search foo"]);
+        assert!(commands("Texas: search foo", &prefix).is_empty());
+        assert!(commands("tex:", &prefix).is_empty());
+    }
+
+    #[test]
+    fn parses_custom_prefixes() {
+        let prefix = Prefix {
+            prefixes: "? ~~".to_owned(),
+            brackets: false,
+            ..Prefix::new("", "#a")
+        };
+        assert_eq!(commands("~~w Enron", &prefix), vec!["w Enron"]);
+        assert_eq!(commands("?w Enron", &prefix), vec!["w Enron"]);
+        assert!(commands(".w Enron", &prefix).is_empty());
+        assert!(commands("the new [w Enron]", &prefix).is_empty());
+    }
+
+    #[test]
+    fn stores_choices_right_away() {
+        let mut cmds = Commands::new(&establish_connection());
+        let mut db = Db::default();
+        let ctx = Context::default();
+        run(&mut cmds, "serch tree", false, &ctx, &mut db, &Offline);
+        assert!(db.choices.get_mut(&ctx).is_some());
+    }
+
+    #[test]
+    fn only_suggests_when_called_directly() {
+        let prefix = Prefix::new("", "#site19");
+        assert!(!get_commands(".serch tree", "tex", &prefix).1);
+        assert!(!get_commands("tex: serch tree", "tex", &prefix).1);
+        assert!(get_commands("as I said [see above]", "tex", &prefix).1);
+        let mut cmds = Commands::new(&establish_connection());
+        let mut db = Db::default();
+        let ctx = Context::default();
+        run(&mut cmds, "seee above", true, &ctx, &mut db, &Offline);
+        assert!(db.choices.get_mut(&ctx).is_none());
     }
 }
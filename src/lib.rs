@@ -1,21 +1,29 @@
 #[macro_use] extern crate diesel;
 
 use irc::client::prelude::*;
+use std::cell::RefCell;
 use std::io;
 use std::io::BufRead;
+use std::rc::Rc;
 
 #[macro_use] mod logging;
+mod args;
 mod auth;
 mod background;
+mod choices;
 mod command;
 mod context;
 mod db;
 mod error;
 mod env;
 mod output;
+mod limit;
+mod links;
 mod local;
 mod handler;
+mod text;
 mod wikidot; 
+mod worker;
 
 use self::context::Context;
 use self::db::{Db, Pool, establish_connection};
@@ -27,8 +35,10 @@ pub use self::env::load;
 #[cfg(test)]
 pub const FUZZ: u16 = 100;
 
-const CAPABILITIES: [Capability; 3] =
-    [ Capability::ChgHost
+const CAPABILITIES: [Capability; 5] =
+    [ Capability::AccountNotify
+    , Capability::AccountTag
+    , Capability::ChgHost
     , Capability::ExtendedJoin
     , Capability::MultiPrefix
     ];
@@ -48,20 +58,36 @@ fn init(pool: Pool) -> IO<Db> {
     Ok(db)
 }
 
+/// Connects to every network at once. The reactor drives all of them from one thread, so they
+/// can share commands and the database without locking.
 pub fn run() -> IO<()> {
     let pool = establish_connection();
-    let mut cmds = Commands::new(&pool);
-    let mut db = init(pool)?;
+    let cmds = Rc::new(RefCell::new(Commands::new(&pool)));
+    let db = Rc::new(RefCell::new(init(pool)?));
 
     let mut reactor = IrcReactor::new()?;
-    let client = reactor.prepare_client_and_connect(&env::irc())?;
-    client.send_cap_req(&CAPABILITIES)?;
-    client.identify()?;
+    let mut clients = Vec::new();
+    for network in env::networks() {
+        let client = reactor.prepare_client_and_connect(&network.config)?;
+        client.send_cap_req(&CAPABILITIES)?;
+        client.identify()?;
+        clients.push((network.name, client));
+    }
 
-    reactor.register_client_with_handler(
-        client, 
-        move |c, m| handler::handle(m, &mut cmds, c, &mut db)
-    );
+    let clients = Rc::new(clients);
+    for (name, client) in clients.iter() {
+        let name = name.to_owned();
+        let clients = Rc::clone(&clients);
+        let cmds = Rc::clone(&cmds);
+        let db = Rc::clone(&db);
+        reactor.register_client_with_handler(client.clone(), move |c, m| {
+            let mut db = db.borrow_mut();
+            for (network, client) in clients.iter() {
+                handler::announce(network, client, &mut db);
+            }
+            handler::handle(m, &name, &mut cmds.borrow_mut(), c, &mut db)
+        });
+    }
     reactor.run()?;
 
     Ok(())
@@ -80,7 +106,8 @@ pub fn offline() -> IO<()> {
             ":Jabyrwock!~jabyrwock@7B468DF6:FEE59C82:7ED85AB8:IP PRIVMSG #projectfreelancer :{}",
             line?
         ).parse()?;
-        handler::handle(message, &mut cmds, &client, &mut db)?;
+        handler::announce(env::MAIN, &client, &mut db);
+        handler::handle(message, env::MAIN, &mut cmds, &client, &mut db)?;
     }
     Ok(())
 }
@@ -0,0 +1,187 @@
+use hashbrown::HashMap;
+use std::cmp;
+use std::time::{Duration, Instant};
+
+use crate::Context;
+use crate::db::{RateLimit, scoped};
+use crate::error::*;
+
+const WINDOW: f64 = 60.0;
+/// Any bucket left alone for a whole window has refilled completely.
+const REFILL: Duration = Duration::from_secs(60);
+const BACKOFF: Duration = Duration::from_secs(15);
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 10);
+
+/// Budgets refill continuously, so a budget of 10 allows a burst of 10 commands and then one more
+/// every 6 seconds.
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens:  f64,
+    updated: Instant,
+    strikes: u32,
+    until:   Option<Instant>
+}
+
+impl Bucket {
+    fn new(budget: i32, now: Instant) -> Self {
+        Self { tokens: f64::from(budget), updated: now, strikes: 0, until: None }
+    }
+
+    fn refill(&mut self, budget: i32, now: Instant) {
+        let elapsed = now.duration_since(self.updated);
+        let secs = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_millis()) / 1000.0;
+        let budget = f64::from(budget);
+        self.tokens = (self.tokens + secs * budget / WINDOW).min(budget);
+        self.updated = now;
+    }
+
+    /// Whether the bucket is no different from a new one, so there's no point keeping it.
+    fn full(&self, now: Instant) -> bool {
+        now.duration_since(self.updated) >= REFILL && self.until.map_or(true, |x| now >= x)
+    }
+
+    /// Each strike while already blocked doubles the block, so persistent flooders stay ignored.
+    fn strike(&mut self, now: Instant) {
+        self.strikes += 1;
+        let backoff = BACKOFF * 2u32.pow(cmp::min(self.strikes - 1, 6));
+        self.until = Some(now + cmp::min(backoff, MAX_BACKOFF));
+    }
+}
+
+/// Buckets for each user and each channel, with channels `scoped` by network.
+#[derive(Debug, Default)]
+pub struct Limiter {
+    users:    HashMap<(String, String), Bucket>,
+    channels: HashMap<String, Bucket>,
+    pruned:   Option<Instant>
+}
+
+impl Limiter {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spend(&mut self, ctx: &Context, cost: u32, limit: &RateLimit) -> Result<(), Error> {
+        self.spend_at(ctx, cost, limit, Instant::now())
+    }
+
+    /// Only the first refusal is reported to the user. After that, the bot stays quiet until the 
+    /// user stops.
+    fn spend_at(&mut self, ctx: &Context, cost: u32, limit: &RateLimit, now: Instant) 
+    -> Result<(), Error> {
+        self.prune(now);
+        let cost = f64::from(cost);
+        let user = self.users
+            .entry((scoped(&ctx.network, &ctx.channel), ctx.user.to_owned()))
+            .or_insert_with(|| Bucket::new(limit.per_user, now));
+        if let Some(until) = user.until {
+            if now < until {
+                user.strike(now);
+                return Err(RateLimited(false))
+            }
+            user.until = None;
+        }
+        user.refill(limit.per_user, now);
+        if user.tokens < cost {
+            user.strike(now);
+            return Err(RateLimited(user.strikes == 1))
+        }
+        let channel = self.channels
+            .entry(scoped(&ctx.network, &ctx.channel))
+            .or_insert_with(|| Bucket::new(limit.per_channel, now));
+        channel.refill(limit.per_channel, now);
+        if channel.tokens < cost {
+            return Err(RateLimited(false))
+        }
+        user.strikes = 0;
+        user.tokens -= cost;
+        channel.tokens -= cost;
+        Ok(())
+    }
+
+    /// Drops full buckets at most once a window, so the maps don't keep every user and channel
+    /// that was ever seen.
+    fn prune(&mut self, now: Instant) {
+        if self.pruned.map_or(false, |x| now.duration_since(x) < REFILL) {
+            return
+        }
+        self.pruned = Some(now);
+        self.users.retain(|_, x| !x.full(now));
+        self.channels.retain(|_, x| !x.full(now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(per_user: i32, per_channel: i32) -> RateLimit {
+        RateLimit { channel: String::new(), per_user, per_channel, network: String::new() }
+    }
+
+    fn warned(result: Result<(), Error>) -> Option<bool> {
+        match result {
+            Err(RateLimited(warn)) => Some(warn),
+            _                      => None
+        }
+    }
+
+    #[test]
+    fn warns_once_then_backs_off() {
+        let mut limiter = Limiter::new();
+        let ctx = Context::mock("#site19", "Alice");
+        let limit = limit(3, 100);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.spend_at(&ctx, 1, &limit, now).is_ok());
+        }
+        assert_eq!(warned(limiter.spend_at(&ctx, 1, &limit, now)), Some(true));
+        assert_eq!(warned(limiter.spend_at(&ctx, 1, &limit, now)), Some(false));
+        let later = now + Duration::from_secs(20);
+        assert_eq!(warned(limiter.spend_at(&ctx, 1, &limit, later)), Some(false));
+        let much_later = now + MAX_BACKOFF;
+        assert!(limiter.spend_at(&ctx, 1, &limit, much_later).is_ok());
+    }
+
+    #[test]
+    fn charges_costs() {
+        let mut limiter = Limiter::new();
+        let ctx = Context::mock("#site19", "Alice");
+        let limit = limit(5, 100);
+        let now = Instant::now();
+        assert!(limiter.spend_at(&ctx, 3, &limit, now).is_ok());
+        assert!(limiter.spend_at(&ctx, 3, &limit, now).is_err());
+    }
+
+    #[test]
+    fn shares_channel_budget() {
+        let mut limiter = Limiter::new();
+        let limit = limit(5, 4);
+        let now = Instant::now();
+        assert!(limiter.spend_at(&Context::mock("#a", "Alice"), 2, &limit, now).is_ok());
+        assert!(limiter.spend_at(&Context::mock("#a", "Bob"), 2, &limit, now).is_ok());
+        let carol = limiter.spend_at(&Context::mock("#a", "Carol"), 2, &limit, now);
+        assert_eq!(warned(carol), Some(false));
+        assert!(limiter.spend_at(&Context::mock("#b", "Carol"), 2, &limit, now).is_ok());
+        let elsewhere = Context { network: "synirc".to_owned(), ..Context::mock("#a", "Carol") };
+        assert!(limiter.spend_at(&elsewhere, 2, &limit, now).is_ok());
+    }
+
+    #[test]
+    fn prunes_full_buckets() {
+        let mut limiter = Limiter::new();
+        let limit = limit(1, 100);
+        let now = Instant::now();
+        let (alice, bob) = (Context::mock("#a", "Alice"), Context::mock("#b", "Bob"));
+        assert!(limiter.spend_at(&bob, 1, &limit, now).is_ok());
+        for _ in 0..5 {
+            limiter.spend_at(&alice, 1, &limit, now).ok();
+        }
+        let later = now + REFILL;
+        assert!(limiter.spend_at(&Context::mock("#a", "Carol"), 1, &limit, later).is_ok());
+        // Alice is still blocked, so she's kept.
+        assert_eq!(limiter.users.len(), 2);
+        assert!(!limiter.channels.contains_key(&scoped(&bob.network, &bob.channel)));
+    }
+}
@@ -0,0 +1,135 @@
+use diesel::prelude::*;
+use hashbrown::{HashMap, HashSet};
+use regex::Regex;
+use std::time::{Duration, Instant};
+
+use crate::{Context, IO, util};
+use crate::db::{Db, Page, page, scoped};
+use crate::output::Response;
+use crate::text::Text;
+
+/// The name used to disable link expansion in a channel, as if it were a command.
+pub const CANON: &str = "links";
+const COOLDOWN: Duration = Duration::from_secs(60 * 10);
+const LIMIT: usize = 3;
+
+pub struct Links {
+    url:    Regex,
+    scp:    Regex,
+    recent: HashMap<String, (String, Instant)>
+}
+
+impl Links {
+    pub fn new(root: &str, site: &str) -> Self {
+        Self {
+            url: Regex::new(&format!(
+                "(?i)\\b(?:{}|{}\\.wikidot\\.com)/([a-z0-9_:-]+)",
+                regex::escape(root.trim_start_matches("www.")), regex::escape(site)
+            )).expect("URL regex failed to compile"),
+            scp: Regex::new("(?i)\\bscp-\\d{3,4}(?:-[a-z]+)?\\b")
+                .expect("SCP regex failed to compile"),
+            recent: HashMap::new()
+        }
+    }
+
+    /// Page names mentioned in a message, each paired with whether it was already a link.
+    pub fn mentions(&self, message: &str) -> Vec<(String, bool)> {
+        let mut mentions = Vec::new();
+        for cap in self.url.captures_iter(message) {
+            mentions.push((cap[1].to_lowercase(), true));
+        }
+        let unlinked = self.url.replace_all(message, " ");
+        for found in self.scp.find_iter(&unlinked) {
+            mentions.push((found.as_str().to_lowercase(), false));
+        }
+        let mut seen = HashSet::new();
+        mentions.retain(|(id, _)| seen.insert(id.to_owned()));
+        mentions.truncate(LIMIT);
+        mentions
+    }
+
+    /// Records an expansion, unless the page was the last one expanded in the channel.
+    pub fn cool(&mut self, channel: &str, id: &str) -> bool {
+        let repeat = match self.recent.get(channel) {
+            None               => false,
+            Some((last, time)) => last == id && time.elapsed() < COOLDOWN
+        };
+        if !repeat {
+            self.recent.insert(channel.to_owned(), (id.to_owned(), Instant::now()));
+        }
+        !repeat
+    }
+}
+
+pub fn expand(ctx: &Context, message: &str, db: &mut Db) -> IO<Vec<Response>> {
+    let mut responses = Vec::new();
+    if db.silenced(ctx, CANON) {
+        return Ok(responses)
+    }
+    let mentions = db.links.mentions(message);
+    if mentions.is_empty() {
+        return Ok(responses)
+    }
+    let conn = db.conn()?;
+    for (id, linked) in mentions {
+        if let Some(page) = page::table.find(&id).first::<Page>(&conn).optional()? {
+            if db.links.cool(&scoped(&ctx.network, &ctx.channel), &page.id) {
+                responses.push(Response::Reply(describe(&page, linked, db)));
+            }
+        }
+    }
+    Ok(responses)
+}
+
+fn describe(page: &Page, linked: bool, db: &Db) -> Text {
+    let text = Text::new()
+        .bold(db.title(page))
+        .plain(" (")
+        .bold(util::rating(i64::from(page.rating)))
+        .plain(format!(") by {}", page.created_by));
+    if linked {
+        text
+    } else {
+        text.plain(" - ").link(format!("http://{}/{}", db.wiki.root, page.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links() -> Links {
+        Links::new("www.scp-wiki.net", "scp-wiki")
+    }
+
+    #[test]
+    fn finds_links() {
+        assert_eq!(
+            links().mentions("see http://www.scp-wiki.net/scp-173 and scp-wiki.wikidot.com/Tale"),
+            vec![("scp-173".to_owned(), true), ("tale".to_owned(), true)]
+        );
+    }
+
+    #[test]
+    fn finds_numbers() {
+        assert_eq!(
+            links().mentions("SCP-3000 is scarier than scp-173-j, but SCP-3000 is long"),
+            vec![("scp-3000".to_owned(), false), ("scp-173-j".to_owned(), false)]
+        );
+    }
+
+    #[test]
+    fn ignores_other_sites() {
+        assert!(links().mentions("http://example.com/scp-wiki/x and SCP-12").is_empty());
+    }
+
+    #[test]
+    fn cools_down() {
+        let mut links = links();
+        assert!(links.cool("#site19", "scp-173"));
+        assert!(!links.cool("#site19", "scp-173"));
+        assert!(links.cool("#site17", "scp-173"));
+        assert!(links.cool("#site19", "scp-096"));
+        assert!(links.cool("#site19", "scp-173"));
+    }
+}
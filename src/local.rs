@@ -43,6 +43,10 @@ impl<T: Local + Eq + Hash> LocalMap<T> {
     pub fn get_mut(&mut self, channel: &str, user: &str) -> Option<&mut T> {
         self.0.get_mut(channel)?.get_mut(user)
     }
+    /// Everything stored for a channel.
+    pub fn in_channel(&self, channel: &str) -> Vec<&T> {
+        self.0.get(channel).map_or_else(Vec::new, |x| x.values().collect())
+    }
     #[inline]
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
@@ -33,18 +33,13 @@ fn label(lvl: Level) -> &'static str {
     }
 }
 
-#[inline] 
-fn clean(s: &str) -> String {
-    s.replace('\x02',"").replace('\x1d', "")
-}
-
 #[inline]
 pub fn log(lvl: Level, s: &str) {
-    println!("\x1b[{}m{}{}\x1b[0m", color(lvl), label(lvl), clean(s));
+    println!("\x1b[{}m{}{}\x1b[0m", color(lvl), label(lvl), s);
 }
 #[inline]
 pub fn log_part(lvl: Level, s: &str) {
-    print!("\x1b[{}m{}{}\x1b[0m", color(lvl), label(lvl), clean(s));
+    print!("\x1b[{}m{}{}\x1b[0m", color(lvl), label(lvl), s);
 }
 
 pub trait Logged {
@@ -2,38 +2,115 @@ use irc::client::prelude::*;
 use irc::error::IrcError;
 use irc::client::data::user::AccessLevel;
 use irc::client::data::user::AccessLevel::*;
+use std::cmp;
 
 use crate::logging::*;
 use crate::Context;
 use crate::auth::Auth;
+use crate::text::{Format, Text};
 
 use self::Response::*;
 
+/// The most bytes the server will relay in one line, counting the command, the target and the
+/// trailing CRLF.
+const LINE_LIMIT: usize = 512;
+/// Room for the nick, username and host the server adds to each line the bot sends, which it
+/// can't see from here.
+const SOURCE_RESERVE: usize = 100;
+/// Lines of a long response past this are held for `.more` instead of flooding the channel.
+pub const MAX_LINES: usize = 3;
+/// Marks the last line shown when there's more to come.
+pub const MORE: &str = " [more]";
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Response {
-    Action(String),
-    Ban(String),
-    Message(String),
-    Quit(String),
-    Reply(String)
+    Action(Text),
+    Ban(Text),
+    Message(Text),
+    Notice(Text),
+    Quit(Text),
+    Reply(Text)
 }
 
-#[cfg(test)]
 impl Response {
-    pub fn text(&self) -> &str {
+    /// The response's text, and a way to make another response of the same kind.
+    fn into_parts(self) -> (fn(Text) -> Response, Text) {
+        match self {
+            Action(s)  => (Action, s),
+            Ban(s)     => (Ban, s),
+            Message(s) => (Message, s),
+            Notice(s)  => (Notice, s),
+            Quit(s)    => (Quit, s),
+            Reply(s)   => (Reply, s)
+        }
+    }
+
+    pub fn push_str(&mut self, suffix: &str) {
+        match self {
+            Action(s) | Ban(s) | Message(s) | Notice(s) | Quit(s) | Reply(s) => s.push_str(suffix)
+        }
+    }
+
+    /// The response's text as IRC would show it.
+    #[cfg(test)]
+    pub fn text(&self) -> String {
         match self {
             Action(s) => s,
             Ban(s) => s,
             Message(s) => s,
+            Notice(s) => s,
             Quit(s) => s,
             Reply(s) => s
-        }
+        }.render(Format::Irc)
     }
 }
 
 pub trait Output {
     fn auth(&self, ctx: &Context) -> Auth;
-    fn respond(&self, ctx: &Context, response: Response) -> Result<(), IrcError>;
+    /// How responses should be rendered.
+    fn format(&self) -> Format;
+    /// Sends a response as it is. Use `respond` instead, which makes sure it fits.
+    fn send(&self, ctx: &Context, response: Response) -> Result<(), IrcError>;
+
+    /// Splits a response into lines that fit and sends the first few. Returns the rest, which
+    /// should be held until the user asks for `.more`.
+    fn respond(&self, ctx: &Context, response: Response) -> Result<Vec<Response>, IrcError> {
+        let (lines, held) = self.paginate(ctx, response);
+        for line in lines {
+            self.send(ctx, line)?;
+        }
+        Ok(held)
+    }
+
+    /// Splits a response into the lines that fit on the first page and the ones held after it.
+    fn paginate(&self, ctx: &Context, response: Response) -> (Vec<Response>, Vec<Response>) {
+        let room = match overhead(ctx, &response) {
+            Some(overhead) => LINE_LIMIT.saturating_sub(overhead),
+            None           => return (vec![response], Vec::new())
+        };
+        let (kind, text) = response.into_parts();
+        let mut lines = text.split(room, self.format());
+        if lines.len() > MAX_LINES {
+            // Every line needs room for the marker, since any of them might end a page.
+            lines = text.split(room.saturating_sub(MORE.len()), self.format());
+            lines[MAX_LINES - 1].push_str(MORE);
+        }
+        let held = lines.split_off(cmp::min(lines.len(), MAX_LINES));
+        (lines.into_iter().map(kind).collect(), held.into_iter().map(kind).collect())
+    }
+}
+
+/// How many bytes of a line aren't the response's text, or `None` if it isn't sent as a message.
+fn overhead(ctx: &Context, response: &Response) -> Option<usize> {
+    let (command, target, extra) = match response {
+        Action(_)  => ("PRIVMSG", &ctx.channel, "\x01ACTION \x01".len()),
+        Message(_) => ("PRIVMSG", &ctx.user, 0),
+        Notice(_)  => ("NOTICE", &ctx.channel, 0),
+        Reply(_) if ctx.channel == ctx.user => ("PRIVMSG", &ctx.user, ctx.nick.len() + 2),
+        Reply(_)   => ("NOTICE", &ctx.channel, ctx.nick.len() + 2),
+        Ban(_) | Quit(_) => return None
+    };
+    Some(SOURCE_RESERVE + command.len() + 1 + target.len() + " :".len() + extra + "\r\n".len())
 }
 
 fn access(irc: &IrcClient, ctx: &Context) -> Option<AccessLevel> {
@@ -52,50 +129,62 @@ impl Output for IrcClient {
             Some(Admin)  => Auth::Op,
             Some(Oper)   => Auth::Op,
             Some(HalfOp) => Auth::HalfOp,
+            Some(Voice)  => Auth::Voice,
             Some(_)      => Auth::Anyone,
             None         => Auth::Anyone
         }
     }
-    fn respond(&self, ctx: &Context, response: Response) -> Result<(), IrcError> {
+    fn format(&self) -> Format {
+        Format::Irc
+    }
+    fn send(&self, ctx: &Context, response: Response) -> Result<(), IrcError> {
         match response {
             Action(msg) => {        
                 log(ECHO, &format!("{}| /me {}", ctx.since(), msg));
-                self.send_action(&ctx.channel, msg)
+                self.send_action(&ctx.channel, msg.render(Format::Irc))
             },
             Ban(msg) => {
                 log(WARNING, &format!(
                     "{}! Banning {} from {}: {}", ctx.since(), ctx.nick, ctx.channel, msg
                 ));
-                self.send_kick(&ctx.channel, &ctx.nick, msg)?;
+                self.send_kick(&ctx.channel, &ctx.nick, msg.render(Format::Irc))?;
                 self.send_mode(&ctx.nick, 
                     &[Mode::Plus(ChannelMode::Ban, Some(ctx.channel.to_owned()))]
                 )
             },
             Message(msg) => {
                 log(ECHO, &format!("{}@ {}", ctx.since(), msg));
-                self.send_privmsg(&ctx.user, msg)
+                self.send_privmsg(&ctx.user, msg.render(Format::Irc))
+            },
+            Notice(msg) => {
+                log(ECHO, &format!("{}| {}", ctx.since(), msg));
+                self.send_notice(&ctx.channel, msg.render(Format::Irc))
             },
-            Quit(msg) => self.send_quit(msg),
+            Quit(msg) => self.send_quit(msg.render(Format::Irc)),
             Reply(msg) => {
-                let reply = format!("{}: {}", ctx.nick, msg);
+                let reply = Text::new().plain(format!("{}: ", ctx.nick)).append(msg);
                 if ctx.channel == ctx.user {
-                    self.respond(ctx, Message(reply))
+                    self.send(ctx, Message(reply))
                 } else {
                     log(ECHO, &format!("{}| {}", ctx.since(), reply));
-                    self.send_notice(&ctx.channel, reply)
+                    self.send_notice(&ctx.channel, reply.render(Format::Irc))
                 }
             }
         }
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct Offline;
 
 impl Output for Offline {
     fn auth(&self, _: &Context) -> Auth {
         Auth::Owner
     }
-    fn respond(&self, ctx: &Context, response: Response) -> Result<(), IrcError> {
+    fn format(&self) -> Format {
+        Format::Plain
+    }
+    fn send(&self, ctx: &Context, response: Response) -> Result<(), IrcError> {
         match response {
             Action(msg) => {        
                 log(ECHO, &format!("{}| /me {}", ctx.since(), msg));
@@ -111,11 +200,21 @@ impl Output for Offline {
                 log(ECHO, &format!("{}@ {}", ctx.since(), msg));
                 Ok(())
             },
-            Quit(msg) => panic!(msg),
+            Notice(msg) => {
+                log(ECHO, &format!("{}| {}", ctx.since(), msg));
+                Ok(())
+            },
+            // Offline mode used to quit by panicking, which only worked while responses were sent
+            // from the main thread. A panic on a worker thread just kills that lane, so this exits
+            // the whole process instead, with a success status since quitting isn't an error.
+            Quit(msg) => {
+                log(WARNING, &format!("{}! Quitting: {}", ctx.since(), msg));
+                std::process::exit(0)
+            },
             Reply(msg) => {
-                let reply = format!("{}: {}", ctx.nick, msg);
+                let reply = Text::new().plain(format!("{}: ", ctx.nick)).append(msg);
                 if ctx.channel == ctx.user {
-                    self.respond(ctx, Message(reply))
+                    self.send(ctx, Message(reply))
                 } else {
                     log(ECHO, &format!("{}| {}", ctx.since(), reply));
                     Ok(())
@@ -124,3 +223,16 @@ impl Output for Offline {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_the_whole_line() {
+        let ctx = Context::mock("#site19", "Alice");
+        let reply = overhead(&ctx, &Reply(Text::new())).unwrap();
+        assert_eq!(reply, SOURCE_RESERVE + "NOTICE #site19 :Alice: \r\n".len());
+        assert_eq!(overhead(&ctx, &Quit(Text::new())), None);
+    }
+}
@@ -0,0 +1,303 @@
+use std::fmt;
+
+use self::Span::*;
+
+/// How an output shows formatting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// Control codes, which IRC clients turn into formatting.
+    Irc,
+    /// No formatting at all, for logs and the offline console.
+    Plain,
+    /// Markdown, for chat services that speak it.
+    Markdown
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Span {
+    Plain(String),
+    Bold(String),
+    Italic(String),
+    Link(String)
+}
+
+impl Span {
+    fn as_str(&self) -> &str {
+        match self {
+            Plain(s) | Bold(s) | Italic(s) | Link(s) => s
+        }
+    }
+
+    /// A span of the same kind with different text.
+    fn with(&self, s: String) -> Span {
+        match self {
+            Plain(_)  => Plain(s),
+            Bold(_)   => Bold(s),
+            Italic(_) => Italic(s),
+            Link(_)   => Link(s)
+        }
+    }
+
+    fn render(&self, format: Format, out: &mut String) {
+        let (open, close) = match (self, format) {
+            (Plain(_), _)                 => ("", ""),
+            (_, Format::Plain)            => ("", ""),
+            (Bold(_), Format::Irc)        => ("\x02", "\x02"),
+            (Bold(_), Format::Markdown)   => ("**", "**"),
+            (Italic(_), Format::Irc)      => ("\x1d", "\x1d"),
+            (Italic(_), Format::Markdown) => ("_", "_"),
+            // IRC clients find links on their own.
+            (Link(_), Format::Irc)        => ("", ""),
+            (Link(_), Format::Markdown)   => ("<", ">")
+        };
+        out.push_str(open);
+        out.push_str(self.as_str());
+        out.push_str(close);
+    }
+}
+
+/// Formatted text, which each output renders in its own way. Build it up with `plain`, `bold`,
+/// `italic` and `link`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Text(Vec<Span>);
+
+impl Text {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a span, merging it into the last one if they look the same.
+    fn push(&mut self, span: Span) {
+        if span.as_str().is_empty() {
+            return
+        }
+        let merged = match (self.0.last_mut(), &span) {
+            (Some(Plain(a)), Plain(b))
+            | (Some(Bold(a)), Bold(b))
+            | (Some(Italic(a)), Italic(b)) => {
+                a.push_str(b);
+                true
+            },
+            _ => false
+        };
+        if !merged {
+            self.0.push(span);
+        }
+    }
+
+    pub fn plain<T: fmt::Display>(mut self, x: T) -> Self {
+        self.push(Plain(x.to_string()));
+        self
+    }
+    pub fn bold<T: fmt::Display>(mut self, x: T) -> Self {
+        self.push(Bold(x.to_string()));
+        self
+    }
+    pub fn italic<T: fmt::Display>(mut self, x: T) -> Self {
+        self.push(Italic(x.to_string()));
+        self
+    }
+    pub fn link<T: fmt::Display>(mut self, url: T) -> Self {
+        self.push(Link(url.to_string()));
+        self
+    }
+    pub fn append(mut self, other: Text) -> Self {
+        for span in other.0 {
+            self.push(span);
+        }
+        self
+    }
+
+    /// Joins items with commas, like "a, b, c".
+    pub fn list<I: IntoIterator<Item = Text>>(items: I) -> Self {
+        let mut text = Self::new();
+        for (i, item) in items.into_iter().enumerate() {
+            if i > 0 {
+                text.push(Plain(", ".to_owned()));
+            }
+            text = text.append(item);
+        }
+        text
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.push(Plain(s.to_owned()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        let mut out = String::new();
+        for span in &self.0 {
+            span.render(format, &mut out);
+        }
+        out
+    }
+
+    /// How many bytes the text takes up once rendered.
+    pub fn width(&self, format: Format) -> usize {
+        self.render(format).len()
+    }
+
+    fn trim_end(mut self) -> Self {
+        while let Some(last) = self.0.pop() {
+            let trimmed = last.as_str().trim_end().to_owned();
+            if !trimmed.is_empty() {
+                self.0.push(last.with(trimmed));
+                break
+            }
+        }
+        self
+    }
+
+    /// Breaks the text into words, each with the spaces that follow it. Links are never broken.
+    fn words(&self) -> Vec<Text> {
+        let mut words = Vec::new();
+        let mut word = Text::new();
+        for span in &self.0 {
+            if let Link(_) = span {
+                word.push(span.to_owned());
+                continue
+            }
+            let s = span.as_str();
+            let mut start = 0;
+            for (i, _) in s.match_indices(' ') {
+                word.push(span.with(s[start..=i].to_owned()));
+                words.push(word);
+                word = Text::new();
+                start = i + 1;
+            }
+            word.push(span.with(s[start..].to_owned()));
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+        words
+    }
+
+    /// Splits the longest start of the text that fits in `room` off from the rest, by character.
+    fn split_at_width(&self, room: usize, format: Format) -> (Text, Text) {
+        let mut head = Text::new();
+        let mut tail = Text::new();
+        for span in &self.0 {
+            for c in span.as_str().chars() {
+                let piece = span.with(c.to_string());
+                let mut longer = head.clone();
+                longer.push(piece.to_owned());
+                if tail.is_empty() && (head.is_empty() || longer.width(format) <= room) {
+                    head = longer;
+                } else {
+                    tail.push(piece);
+                }
+            }
+        }
+        (head, tail)
+    }
+
+    /// Splits the text into lines that take up at most `room` bytes once rendered. Lines break
+    /// between words where possible, and formatting carries over from one line to the next.
+    pub fn split(&self, room: usize, format: Format) -> Vec<Text> {
+        let mut lines = Vec::new();
+        let mut line = Text::new();
+        for word in self.words() {
+            let longer = line.clone().append(word.clone());
+            if line.is_empty() || longer.clone().trim_end().width(format) <= room {
+                line = longer;
+            } else {
+                lines.push(line.trim_end());
+                line = word;
+            }
+            while line.clone().trim_end().width(format) > room {
+                let (head, tail) = line.split_at_width(room, format);
+                if tail.is_empty() {
+                    break
+                }
+                lines.push(head);
+                line = tail;
+            }
+        }
+        let line = line.trim_end();
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+impl From<String> for Text {
+    fn from(s: String) -> Self {
+        Text::new().plain(s)
+    }
+}
+
+impl From<&str> for Text {
+    fn from(s: &str) -> Self {
+        Text::new().plain(s)
+    }
+}
+
+/// Shows the text without formatting.
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render(Format::Plain))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn irc(lines: Vec<Text>) -> Vec<String> {
+        lines.into_iter().map(|x| x.render(Format::Irc)).collect()
+    }
+
+    #[test]
+    fn renders_each_format() {
+        let text = Text::new()
+            .bold("SCP-173")
+            .plain(" by ")
+            .italic("Moto42")
+            .plain(" ")
+            .link("http://scp-wiki.net/scp-173");
+        assert_eq!(
+            text.render(Format::Irc),
+            "\x02SCP-173\x02 by \x1dMoto42\x1d http://scp-wiki.net/scp-173"
+        );
+        assert_eq!(text.render(Format::Plain), "SCP-173 by Moto42 http://scp-wiki.net/scp-173");
+        assert_eq!(
+            text.render(Format::Markdown),
+            "**SCP-173** by _Moto42_ <http://scp-wiki.net/scp-173>"
+        );
+    }
+
+    #[test]
+    fn merges_spans() {
+        let text = Text::new().bold("a").bold("b").plain("").plain("c");
+        assert_eq!(text.render(Format::Irc), "\x02ab\x02c");
+        assert_eq!(Text::list(vec![Text::from("a"), Text::new().bold("b")]).to_string(), "a, b");
+    }
+
+    #[test]
+    fn splits_on_spaces() {
+        assert_eq!(irc(Text::from("aaa bbb ccc").split(7, Format::Irc)), vec!["aaa bbb", "ccc"]);
+        assert_eq!(irc(Text::from("aaaaaaaaaa").split(4, Format::Irc)), vec!["aaaa", "aaaa", "aa"]);
+    }
+
+    #[test]
+    fn keeps_characters_whole() {
+        for line in Text::from("é".repeat(10)).split(5, Format::Irc) {
+            assert_eq!(line.render(Format::Irc), "éé");
+        }
+    }
+
+    #[test]
+    fn carries_formatting() {
+        let text = Text::new().bold("bold words").plain(" plain");
+        let lines = irc(text.split(8, Format::Irc));
+        assert_eq!(lines, vec!["\x02bold\x02", "\x02words\x02", "plain"]);
+        assert_eq!(text.split(10, Format::Plain).len(), 2);
+    }
+}
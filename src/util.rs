@@ -6,8 +6,6 @@ use std::iter::*;
 use std::string::ToString;
 use std::time::{Instant, SystemTime};
 
-const CHARACTER_LIMIT: usize = 300;
-
 #[inline]
 pub fn encode(s: &str) -> String {
     utf8_percent_encode(s, percent_encoding::DEFAULT_ENCODE_SET).to_string()
@@ -18,10 +16,11 @@ pub fn own(xs: &[&str]) -> Vec<String> {
     xs.into_iter().map(ToString::to_string).collect()
 }
 
-pub fn trim(s: &str) -> String {
+/// Cuts text down to about `room` bytes, ending at a space and marking the cut.
+pub fn trim(s: &str, room: usize) -> String {
     let mut content = s.to_owned();
-    if content.len() > CHARACTER_LIMIT {
-        if let Some(i) = content[..CHARACTER_LIMIT-4].rfind(' ') {
+    if content.len() > room {
+        if let Some(i) = content[..room.saturating_sub(4)].rfind(' ') {
             content = content[..i].to_owned();
         }
         content.push_str(" […]");
@@ -30,11 +29,10 @@ pub fn trim(s: &str) -> String {
 }
 
 #[inline]
+/// Shows a rating with its sign, like +5, 0 or -3.
 pub fn rating(i: i64) -> String {
     if i > 0 {
         format!("+{}", i)
-    } else if i < 0 {
-        format!("-{}", i)
     } else {
         format!("{}", i)
     }
@@ -87,7 +85,18 @@ fn show_ago(amount: i64, s: &str) -> String {
 }
 
 pub fn ago<T: DurationAgo>(time: T) -> String {
-    let dur = time.duration_ago();
+    show_duration(time.duration_ago())
+}
+
+/// How long until a time in the future, in the same style as `ago`.
+pub fn until(time: SystemTime) -> String {
+    match time.duration_since(SystemTime::now()) {
+        Err(_)  => show_duration(Duration::zero()),
+        Ok(dur) => show_duration(Duration::seconds(dur.as_secs() as i64))
+    }
+}
+
+fn show_duration(dur: Duration) -> String {
     if dur.num_days() > 365 {
         show_ago(dur.num_days() / 365, "year")
     } else if dur.num_weeks() > 0 {
@@ -105,6 +114,36 @@ pub fn ago<T: DurationAgo>(time: T) -> String {
     }
 }
 
+/// The longest duration `parse_duration` accepts, a year.
+pub const MAX_DURATION: u64 = 365 * 24 * 60 * 60;
+
+/// Parses days, hours and minutes like 1d4h30m. Each unit is optional, but they have to come in
+/// that order.
+pub fn parse_duration(s: &str) -> Option<std::time::Duration> {
+    let mut units = [('d', 24 * 60 * 60), ('h', 60 * 60), ('m', 60)].iter();
+    let mut number = String::new();
+    let mut secs: u64 = 0;
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue
+        }
+        let &(_, scale) = units.find(|&&(unit, _)| unit == c)?;
+        let amount: u64 = number.parse().ok()?;
+        secs = secs.checked_add(amount.checked_mul(scale)?)?;
+        number.clear();
+    }
+    if s.is_empty() || !number.is_empty() || secs > MAX_DURATION {
+        return None
+    }
+    Some(std::time::Duration::from_secs(secs))
+}
+
+/// The time a duration like 1d4h30m from now.
+pub fn after(s: &str) -> Option<SystemTime> {
+    SystemTime::now().checked_add(parse_duration(s)?)
+}
+
 pub fn show_time(time: SystemTime) -> String {
     let str = humantime::format_rfc3339_seconds(
         time - std::time::Duration::from_secs(60 * 60 * 8)
@@ -143,15 +182,6 @@ pub fn drain_filter<F, T>(vec: &mut Vec<T>, filter: F) -> Vec<T> where F: Fn(&T)
     drained
 }
 
-pub fn pop_filter<F, T>(vec: &mut Vec<T>, filter: F) -> Option<T> where F: Fn(&T) -> bool {
-    for i in 0..vec.len() {
-        if filter(&vec[i]) {
-            return Some(vec.remove(i))
-        }
-    }
-    None
-}
-
 pub fn multi_remove<K: Eq + Hash, V: Eq>(map: &mut MultiMap<K, V>, k: &K, v: &V) -> bool {
     if let Some(vec) = map.get_vec_mut(k) {
         let mut i = 0;
@@ -167,6 +197,52 @@ pub fn multi_remove<K: Eq + Hash, V: Eq>(map: &mut MultiMap<K, V>, k: &K, v: &V)
     false
 }
 
+/// Matches wildcards in IRC masks, where `*` stands for any run of characters and `?` for any
+/// single one.
+pub fn glob(pattern: &str, s: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+    let (mut i, mut j) = (0, 0);
+    // Where the last star was, and where in `s` it stopped matching.
+    let mut star = None;
+    while j < s.len() {
+        match pattern.get(i) {
+            Some('*') => {
+                star = Some((i, j));
+                i += 1;
+            },
+            Some(&c) if c == '?' || c == s[j] => {
+                i += 1;
+                j += 1;
+            },
+            _ => match star {
+                None             => return false,
+                Some((at, from)) => {
+                    star = Some((at, from + 1));
+                    i = at + 1;
+                    j = from + 1;
+                }
+            }
+        }
+    }
+    pattern[i..].iter().all(|&c| c == '*')
+}
+
+/// Levenshtein distance, counting characters rather than bytes.
+pub fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &y) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if x == y { diagonal } else { 1 + diagonal.min(above).min(row[j]) };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Gender {
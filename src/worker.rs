@@ -0,0 +1,183 @@
+use hashbrown::HashMap;
+use std::sync::mpsc::{Receiver, SendError, Sender, channel};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::Context;
+use crate::choices::Choices;
+use crate::logging::*;
+use crate::output::{Output, Response};
+
+pub type Job<T> = Box<dyn FnOnce() -> T + Send>;
+
+type Task = (Context, Job<Vec<Response>>);
+type Held = (Context, Vec<Response>);
+
+/// Lanes that haven't been sent anything for this long are closed. Otherwise every channel and
+/// private message the bot ever answered would keep a thread around for good.
+const IDLE: Duration = Duration::from_secs(60 * 10);
+
+/// Runs jobs off the IRC thread. Each channel gets its own lane, which runs that channel's jobs
+/// one at a time in the order they were sent, so responses never arrive out of order. Lanes are
+/// keyed by network as well, since each one sends through its own network's client.
+pub struct Workers {
+    lanes:   HashMap<(String, String), (Sender<Task>, Instant)>,
+    choices: Sender<(Context, Choices)>,
+    held:    Sender<Held>
+}
+
+impl Workers {
+    pub fn build() -> (Self, Receiver<(Context, Choices)>, Receiver<Held>) {
+        let (choices, choices_r) = channel();
+        let (held, held_r) = channel();
+        (Self { lanes: HashMap::new(), choices, held }, choices_r, held_r)
+    }
+
+    /// Lanes can't touch the database, so they send disambiguation choices back through this.
+    pub fn choices(&self) -> Sender<(Context, Choices)> {
+        self.choices.clone()
+    }
+
+    pub fn run<O>(&mut self, irc: &O, ctx: &Context, job: Job<Vec<Response>>)
+    where O: Output + Clone + Send + 'static {
+        // Dropping a lane's sender lets its thread finish whatever is queued and then exit.
+        self.lanes.retain(|_, (_, time)| time.elapsed() < IDLE);
+        let key = (ctx.network.to_owned(), ctx.channel.to_owned());
+        let task = match self.lanes.get_mut(&key) {
+            None               => (ctx.to_owned(), job),
+            Some((lane, time)) => match lane.send((ctx.to_owned(), job)) {
+                Ok(())               => { *time = Instant::now(); return },
+                Err(SendError(task)) => task
+            }
+        };
+        // The lane is new, was closed for being idle, or its thread panicked, so start a new one.
+        let lane = spawn(irc.clone(), self.held.clone());
+        lane.send(task).log(trace!());
+        self.lanes.insert(key, (lane, Instant::now()));
+    }
+
+    pub fn respond<O>(&mut self, irc: &O, ctx: &Context, responses: Vec<Response>)
+    where O: Output + Clone + Send + 'static {
+        if !responses.is_empty() {
+            self.run(irc, ctx, Box::new(move || responses));
+        }
+    }
+}
+
+/// Lines that don't fit are sent back through `held`, to wait for `.more`.
+fn spawn<O: Output + Send + 'static>(irc: O, held: Sender<Held>) -> Sender<Task> {
+    let (sender, receiver) = channel::<Task>();
+    thread::spawn(move || {
+        for (ctx, job) in receiver {
+            for response in job() {
+                match irc.respond(&ctx, response) {
+                    Err(e) => log(ERROR, &format!("Error sending response: {}", e)),
+                    Ok(lines) => if !lines.is_empty() {
+                        held.send((ctx.to_owned(), lines)).log(trace!());
+                    }
+                }
+            }
+        }
+    });
+    sender
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use irc::error::IrcError;
+    use std::sync::{Arc, Mutex};
+
+    use crate::auth::Auth;
+    use crate::output::MAX_LINES;
+    use crate::text::Format;
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Output for Recorder {
+        fn auth(&self, _: &Context) -> Auth {
+            Auth::Anyone
+        }
+        fn format(&self) -> Format {
+            Format::Plain
+        }
+        fn send(&self, _: &Context, response: Response) -> Result<(), IrcError> {
+            self.0.lock().unwrap().push(response.text());
+            Ok(())
+        }
+    }
+
+    /// A job that waits for the returned sender before replying.
+    fn blocked(reply: &'static str) -> (Sender<()>, Job<Vec<Response>>) {
+        let (gate, gate_r) = channel();
+        (gate, Box::new(move || {
+            gate_r.recv().unwrap();
+            vec![Response::Reply(reply.into())]
+        }))
+    }
+
+    /// Queues a job behind everything else in the lane and waits for it to run.
+    fn finish(workers: &mut Workers, recorder: &Recorder, ctx: &Context) {
+        let (done, done_r) = channel();
+        workers.run(recorder, ctx, Box::new(move || {
+            done.send(()).unwrap();
+            Vec::new()
+        }));
+        done_r.recv_timeout(Duration::from_secs(1)).unwrap();
+    }
+
+    #[test]
+    fn preserves_order() {
+        let recorder = Recorder::default();
+        let (mut workers, _, _) = Workers::build();
+        let ctx = Context::default();
+        let (gate, slow) = blocked("slow");
+        workers.run(&recorder, &ctx, slow);
+        workers.respond(&recorder, &ctx, vec![Response::Reply("fast".into())]);
+        gate.send(()).unwrap();
+        finish(&mut workers, &recorder, &ctx);
+        assert_eq!(*recorder.0.lock().unwrap(), vec!["slow".to_owned(), "fast".to_owned()]);
+    }
+
+    #[test]
+    fn holds_long_responses() {
+        let recorder = Recorder::default();
+        let (mut workers, _, held_r) = Workers::build();
+        let ctx = Context::mock("#site19", "Alice");
+        workers.respond(&recorder, &ctx, vec![Response::Reply("word ".repeat(1000).into())]);
+        let (held_ctx, lines) = held_r.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(held_ctx.user, ctx.user);
+        assert!(!lines.is_empty());
+        assert_eq!(recorder.0.lock().unwrap().len(), MAX_LINES);
+    }
+
+    #[test]
+    fn runs_channels_concurrently() {
+        let recorder = Recorder::default();
+        let (mut workers, _, _) = Workers::build();
+        let (a, b) = (Context::mock("#a", "x"), Context::mock("#b", "x"));
+        let (gate, slow) = blocked("slow");
+        workers.run(&recorder, &a, slow);
+        workers.respond(&recorder, &b, vec![Response::Reply("fast".into())]);
+        // This would time out if #b were stuck behind #a.
+        finish(&mut workers, &recorder, &b);
+        gate.send(()).unwrap();
+        finish(&mut workers, &recorder, &a);
+        assert_eq!(*recorder.0.lock().unwrap(), vec!["fast".to_owned(), "slow".to_owned()]);
+    }
+
+    #[test]
+    fn keeps_networks_apart() {
+        let (main, other) = (Recorder::default(), Recorder::default());
+        let (mut workers, _, _) = Workers::build();
+        let ctx = Context::mock("#site19", "x");
+        let elsewhere = Context { network: "other".to_owned(), ..ctx.to_owned() };
+        workers.respond(&main, &ctx, vec![Response::Reply("main".into())]);
+        workers.respond(&other, &elsewhere, vec![Response::Reply("other".into())]);
+        finish(&mut workers, &main, &ctx);
+        finish(&mut workers, &other, &elsewhere);
+        assert_eq!(*main.0.lock().unwrap(), vec!["main".to_owned()]);
+        assert_eq!(*other.0.lock().unwrap(), vec!["other".to_owned()]);
+    }
+}